@@ -0,0 +1,205 @@
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::error::{Error, Result};
+use crate::path::Path;
+use crate::value::{OutOfRange, SetOptions, SetPolicy, Value};
+
+/// Builds a [`Value`] from environment variables, to be layered over other
+/// sources with [`Value::merge`].
+///
+/// With the prefix `APP` and the default separator `__`, `APP__DB__HOST`
+/// becomes `/db/host` and `APP__HOSTS__0` becomes `/hosts[0]`. Segments are
+/// lowercased, integer segments become array indices, and values are parsed
+/// into the most specific type: boolean, integer, float, or else string.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    prefix: String,
+    separator: String,
+    lowercase: bool,
+    parse_values: bool,
+    vars: Option<Vec<(String, String)>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            prefix: String::new(),
+            separator: "__".to_string(),
+            lowercase: true,
+            parse_values: true,
+            vars: None,
+        }
+    }
+}
+
+impl Environment {
+    /// Reads every variable of the process environment.
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    /// Reads only the variables starting with `prefix` and the separator.
+    pub fn with_prefix<S: Into<String>>(prefix: S) -> Self {
+        Environment::new().prefix(prefix)
+    }
+
+    /// Sets the prefix, see [`Environment::with_prefix`].
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets the separator between segments, `__` by default.
+    pub fn separator<S: Into<String>>(mut self, separator: S) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Sets whether segments are lowercased, `true` by default.
+    pub fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Sets whether values are parsed into booleans and numbers, `true` by
+    /// default. Otherwise every value is a string.
+    pub fn parse_values(mut self, parse_values: bool) -> Self {
+        self.parse_values = parse_values;
+        self
+    }
+
+    /// Reads `vars` instead of the process environment, e.g. in tests.
+    pub fn source<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.vars = Some(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Returns the path a variable maps to, or `None` if it does not match
+    /// the prefix or has an empty segment.
+    ///
+    /// Only canonical integers such as `0` or `12` become indices; `007`
+    /// stays a key. Fails if the separator is empty or an index does not fit
+    /// in an `isize`.
+    pub fn path_of(&self, name: &str) -> Result<Option<Path>> {
+        if self.separator.is_empty() {
+            return Err(Error::invalid_option("separator", "must not be empty"));
+        }
+        let rest = if self.prefix.is_empty() {
+            name
+        } else {
+            match name
+                .strip_prefix(self.prefix.as_str())
+                .and_then(|rest| rest.strip_prefix(self.separator.as_str()))
+            {
+                Some(rest) => rest,
+                None => return Ok(None),
+            }
+        };
+        if rest.is_empty() {
+            return Ok(None);
+        }
+
+        let mut path = Path::root();
+        for segment in rest.split(self.separator.as_str()) {
+            if segment.is_empty() {
+                return Ok(None);
+            }
+            if is_canonical_integer(segment) {
+                let index: BigInt = segment.parse().expect("digits parse as an integer");
+                match index.to_isize() {
+                    Some(index) => path.push_index(index),
+                    None => return Err(Error::too_large(index)),
+                };
+            } else if self.lowercase {
+                path.push_key(segment.to_lowercase());
+            } else {
+                path.push_key(segment);
+            }
+        }
+        Ok(Some(path))
+    }
+
+    /// Collects the matching variables into a map.
+    ///
+    /// Variables are applied in name order, comparing indices numerically.
+    /// Fails with a conflict error if one variable needs a map or an array
+    /// where another one set a scalar, e.g. `APP__DB=x` and `APP__DB__HOST=y`,
+    /// and with an out-of-range error if indices leave a gap, e.g. only
+    /// `APP__HOSTS__1`.
+    pub fn load(&self) -> Result<Value> {
+        let vars: Vec<(String, String)> = match self.vars {
+            Some(ref vars) => vars.clone(),
+            None => std::env::vars_os()
+                .filter_map(|(key, value)| {
+                    Some((key.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+        };
+
+        let mut entries = Vec::new();
+        for (name, raw) in vars {
+            if let Some(path) = self.path_of(&name)? {
+                entries.push((path, raw));
+            }
+        }
+        // Indices in order, so that `__10` comes after `__2`.
+        entries.sort_by(|(a, _), (b, _)| a.cmp_concrete(b));
+
+        let options = SetOptions::new()
+            .policy(SetPolicy::ErrorOnConflict)
+            .out_of_range(OutOfRange::Error);
+        let mut value = Value::Map(Default::default());
+        for (path, raw) in entries {
+            let parsed = if self.parse_values {
+                parse_value(raw)
+            } else {
+                Value::String(raw)
+            };
+            value.set_with(&path, parsed, &options)?;
+        }
+        Ok(value)
+    }
+}
+
+/// Returns whether `segment` is `0` or digits without a leading zero.
+fn is_canonical_integer(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment.bytes().all(|b| b.is_ascii_digit())
+        && (segment == "0" || !segment.starts_with('0'))
+}
+
+/// Parses an environment value into the most specific [`Value`].
+fn parse_value(raw: String) -> Value {
+    match raw.as_str() {
+        "true" | "TRUE" | "True" => return Value::Boolean(true),
+        "false" | "FALSE" | "False" => return Value::Boolean(false),
+        _ => {}
+    }
+
+    // Keep strings like `007`, `+1` or `1_000` as they are.
+    let digits = raw.strip_prefix('-').unwrap_or(&raw);
+    let starts_with_digit = digits.bytes().next().is_some_and(|b| b.is_ascii_digit());
+    if !starts_with_digit
+        || (digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0."))
+    {
+        return Value::String(raw);
+    }
+    if digits.bytes().all(|b| b.is_ascii_digit()) {
+        let value: BigInt = raw.parse().expect("digits parse as an integer");
+        return Value::Integer(value);
+    }
+    match raw.parse::<f64>() {
+        Ok(value) if value.is_finite() => Value::Float(value),
+        _ => Value::String(raw),
+    }
+}
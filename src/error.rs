@@ -7,7 +7,12 @@ use num_bigint::BigInt;
 use serde::de;
 use serde::ser;
 
-#[derive(Debug)]
+use crate::path::{Path, PathParseError};
+use crate::value::Origin;
+
+/// The value found where another type was expected, see
+/// [`Error::unexpected`].
+#[derive(Debug, Clone, PartialEq)]
 pub enum Unexpected {
     Bool(bool),
     Integer(BigInt),
@@ -32,6 +37,36 @@ impl fmt::Display for Unexpected {
     }
 }
 
+/// The category of an [`Error`], see [`Error::kind`].
+///
+/// More kinds may be added in minor releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A path could not be parsed.
+    PathParse,
+    /// Reading or writing failed.
+    Io,
+    /// A value has a different type than requested.
+    Type,
+    /// A JSON, YAML or TOML document could not be parsed or written.
+    FormatParse,
+    /// Serializing or deserializing a value failed.
+    Serde,
+    /// An integer does not fit the requested type.
+    Range,
+    /// A path that must address a single location has wildcards.
+    NotSingular,
+    /// A strict set would have replaced an existing value.
+    Conflict,
+    /// A node on the way of a strict set does not exist.
+    Missing,
+    /// A strict set would have padded an array.
+    OutOfRange,
+    /// A loader was configured with an invalid option.
+    InvalidOption,
+}
+
 pub struct Error {
     inner: Box<ErrorImpl>,
 }
@@ -53,6 +88,129 @@ impl From<std::convert::Infallible> for Error {
 }
 
 impl Error {
+    /// Returns the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match *self.inner {
+            ErrorImpl::PathParse(_) => ErrorKind::PathParse,
+            ErrorImpl::Io(_) => ErrorKind::Io,
+            ErrorImpl::Type { .. } => ErrorKind::Type,
+            ErrorImpl::FormatParse { .. } => ErrorKind::FormatParse,
+            ErrorImpl::Serde { .. } => ErrorKind::Serde,
+            ErrorImpl::Range { .. } => ErrorKind::Range,
+            ErrorImpl::NotSingular(_) => ErrorKind::NotSingular,
+            ErrorImpl::Conflict { .. } => ErrorKind::Conflict,
+            ErrorImpl::Missing(_) => ErrorKind::Missing,
+            ErrorImpl::OutOfRange { .. } => ErrorKind::OutOfRange,
+            ErrorImpl::InvalidOption { .. } => ErrorKind::InvalidOption,
+        }
+    }
+
+    /// Returns the path of the value or location the error is about, if
+    /// known.
+    pub fn path(&self) -> Option<&Path> {
+        match *self.inner {
+            ErrorImpl::Type { ref path, .. }
+            | ErrorImpl::Serde { ref path, .. }
+            | ErrorImpl::Range { ref path, .. } => path.as_ref(),
+            ErrorImpl::NotSingular(ref path)
+            | ErrorImpl::Conflict { ref path, .. }
+            | ErrorImpl::Missing(ref path)
+            | ErrorImpl::OutOfRange { ref path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the value that was found, for type errors and conflicts.
+    pub fn unexpected(&self) -> Option<&Unexpected> {
+        match *self.inner {
+            ErrorImpl::Type { ref unexpected, .. } => Some(unexpected),
+            ErrorImpl::Conflict { ref found, .. } => Some(found),
+            _ => None,
+        }
+    }
+
+    /// Returns what was expected instead, for type errors and conflicts.
+    pub fn expected(&self) -> Option<&'static str> {
+        match *self.inner {
+            ErrorImpl::Type { expected, .. } | ErrorImpl::Conflict { expected, .. } => {
+                Some(expected)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the origin of the value of a type error, see
+    /// [`Tracked::get`](crate::Tracked::get).
+    pub fn origin(&self) -> Option<&Origin> {
+        match *self.inner {
+            ErrorImpl::Type { ref origin, .. } => origin.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the details of a path parse error.
+    pub fn path_parse_error(&self) -> Option<&PathParseError> {
+        match *self.inner {
+            ErrorImpl::PathParse(ref error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a path could not be parsed.
+    pub fn is_path_parse(&self) -> bool {
+        self.kind() == ErrorKind::PathParse
+    }
+
+    /// Returns whether reading or writing failed.
+    pub fn is_io(&self) -> bool {
+        self.kind() == ErrorKind::Io
+    }
+
+    /// Returns whether a value has a different type than requested.
+    pub fn is_type(&self) -> bool {
+        self.kind() == ErrorKind::Type
+    }
+
+    /// Returns whether a JSON, YAML or TOML document could not be parsed or written.
+    pub fn is_format_parse(&self) -> bool {
+        self.kind() == ErrorKind::FormatParse
+    }
+
+    /// Returns whether serializing or deserializing a value failed.
+    pub fn is_serde(&self) -> bool {
+        self.kind() == ErrorKind::Serde
+    }
+
+    /// Returns whether an integer does not fit the requested type.
+    pub fn is_range(&self) -> bool {
+        self.kind() == ErrorKind::Range
+    }
+
+    /// Returns whether a path that must address a single location has wildcards.
+    pub fn is_not_singular(&self) -> bool {
+        self.kind() == ErrorKind::NotSingular
+    }
+
+    /// Returns whether a strict set would have replaced an existing value.
+    pub fn is_conflict(&self) -> bool {
+        self.kind() == ErrorKind::Conflict
+    }
+
+    /// Returns whether a node on the way of a strict set does not exist.
+    pub fn is_missing(&self) -> bool {
+        self.kind() == ErrorKind::Missing
+    }
+
+    /// Returns whether a set would have padded an array beyond what is allowed.
+    pub fn is_out_of_range(&self) -> bool {
+        self.kind() == ErrorKind::OutOfRange
+    }
+
+    /// Returns whether a loader was configured with an invalid option.
+    pub fn is_invalid_option(&self) -> bool {
+        self.kind() == ErrorKind::InvalidOption
+    }
+
     #[doc(hidden)]
     #[cold]
     pub(crate) fn io(source: io::Error) -> Self {
@@ -65,7 +223,10 @@ impl Error {
     #[cold]
     pub(crate) fn too_large<T: Into<BigInt>>(index: T) -> Self {
         Error {
-            inner: Box::new(ErrorImpl::Range(index.into())),
+            inner: Box::new(ErrorImpl::Range {
+                index: index.into(),
+                path: None,
+            }),
         }
     }
 
@@ -76,24 +237,87 @@ impl Error {
             inner: Box::new(ErrorImpl::Type {
                 unexpected,
                 expected,
+                origin: None,
+                path: None,
             }),
         }
     }
 
+    /// Locates a type, range or serde error below `parent`.
+    pub(crate) fn within(mut self, parent: &Path) -> Self {
+        if let Some(slot) = self.inner.path_mut() {
+            *slot = Some(match slot.take() {
+                Some(rest) => parent.join(&rest),
+                None => parent.clone(),
+            });
+        }
+        self
+    }
+
+    /// Records where the value of a type error came from, unless already known.
+    pub(crate) fn with_origin(mut self, origin: Option<&Origin>) -> Self {
+        if let ErrorImpl::Type {
+            origin: ref mut slot @ None,
+            ..
+        } = *self.inner
+        {
+            *slot = origin.cloned();
+        }
+        self
+    }
+
     #[doc(hidden)]
     #[cold]
-    pub(crate) fn path_parse<R: pest::RuleType + Send + Sync + 'static>(
-        source: pest::error::Error<R>,
-        path: &str,
-    ) -> Self {
+    pub(crate) fn not_singular(path: Path) -> Self {
         Error {
-            inner: Box::new(ErrorImpl::PathParse {
-                path: Box::from(path),
-                source: Box::new(source),
+            inner: Box::new(ErrorImpl::NotSingular(path)),
+        }
+    }
+
+    #[doc(hidden)]
+    #[cold]
+    pub(crate) fn conflict(path: Path, found: Unexpected, expected: &'static str) -> Self {
+        Error {
+            inner: Box::new(ErrorImpl::Conflict {
+                path,
+                found,
+                expected,
             }),
         }
     }
 
+    #[doc(hidden)]
+    #[cold]
+    pub(crate) fn missing(path: Path) -> Self {
+        Error {
+            inner: Box::new(ErrorImpl::Missing(path)),
+        }
+    }
+
+    #[doc(hidden)]
+    #[cold]
+    pub(crate) fn out_of_range(path: Path, len: usize) -> Self {
+        Error {
+            inner: Box::new(ErrorImpl::OutOfRange { path, len }),
+        }
+    }
+
+    #[doc(hidden)]
+    #[cold]
+    pub(crate) fn invalid_option(option: &'static str, reason: &'static str) -> Self {
+        Error {
+            inner: Box::new(ErrorImpl::InvalidOption { option, reason }),
+        }
+    }
+
+    #[doc(hidden)]
+    #[cold]
+    pub(crate) fn path_parse(source: PathParseError) -> Self {
+        Error {
+            inner: Box::new(ErrorImpl::PathParse(source)),
+        }
+    }
+
     #[doc(hidden)]
     #[cold]
     pub(crate) fn format_parse<E>(origin: &str, source: E) -> Self
@@ -112,7 +336,10 @@ impl Error {
     #[cold]
     pub(crate) fn serde<T: AsRef<str>>(message: T) -> Self {
         Error {
-            inner: Box::new(ErrorImpl::Serde(message.as_ref().into())),
+            inner: Box::new(ErrorImpl::Serde {
+                message: message.as_ref().into(),
+                path: None,
+            }),
         }
     }
 }
@@ -132,7 +359,7 @@ impl fmt::Debug for Error {
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self.inner {
-            ErrorImpl::PathParse { ref source, .. } => Some(source.as_ref()),
+            ErrorImpl::PathParse(ref source) => Some(source),
 
             ErrorImpl::FormatParse { ref source, .. } => Some(source.as_ref()),
 
@@ -141,19 +368,35 @@ impl StdError for Error {
     }
 }
 
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.path_parse_error().and_then(miette::Diagnostic::code)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.path_parse_error().and_then(miette::Diagnostic::help)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.path_parse_error()
+            .and_then(miette::Diagnostic::source_code)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        self.path_parse_error().and_then(miette::Diagnostic::labels)
+    }
+}
+
 impl de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        Error {
-            inner: Box::new(ErrorImpl::Serde(msg.to_string().into())),
-        }
+        Error::serde(msg.to_string())
     }
 }
 
 impl ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        Error {
-            inner: Box::new(ErrorImpl::Serde(msg.to_string().into())),
-        }
+        Error::serde(msg.to_string())
     }
 }
 
@@ -161,10 +404,7 @@ impl ser::Error for Error {
 /// configuration.
 enum ErrorImpl {
     /// Value path could not be parsed (Origin Path).
-    PathParse {
-        path: Box<str>,
-        source: Box<dyn StdError + Send + Sync>,
-    },
+    PathParse(PathParseError),
 
     /// Some IO error occurred while file operation.
     Io(io::Error),
@@ -176,6 +416,12 @@ enum ErrorImpl {
 
         /// What was expected when parsing the value
         expected: &'static str,
+
+        /// Which source the value came from, if tracked
+        origin: Option<Origin>,
+
+        /// Where the value is, if known
+        path: Option<Path>,
     },
 
     /// Value could not be parsed by target format.
@@ -185,33 +431,143 @@ enum ErrorImpl {
     },
 
     /// Serde error
-    Serde(Box<str>),
+    Serde {
+        message: Box<str>,
+
+        /// Where the value is, if known
+        path: Option<Path>,
+    },
 
     /// Path index over range
-    Range(BigInt),
+    Range {
+        index: BigInt,
+
+        /// Where the value is, if known
+        path: Option<Path>,
+    },
+
+    /// Path may match more than one location where a single one is required.
+    NotSingular(Path),
+
+    /// An existing value is in the way of a strict set.
+    Conflict {
+        /// Where the conflicting value is
+        path: Path,
+
+        /// The value that would have been replaced
+        found: Unexpected,
+
+        /// What the path requires at that location
+        expected: &'static str,
+    },
+
+    /// A node on the way of a strict set does not exist.
+    Missing(Path),
+
+    /// An index of a strict set is past the end of its array.
+    OutOfRange {
+        /// The path up to and including the index
+        path: Path,
+
+        /// The length of the array
+        len: usize,
+    },
+
+    /// A loader option has an invalid value.
+    InvalidOption {
+        /// The name of the option
+        option: &'static str,
+
+        /// What is wrong with its value
+        reason: &'static str,
+    },
+}
+
+impl ErrorImpl {
+    /// Returns the location of errors that are raised while walking a value.
+    fn path_mut(&mut self) -> Option<&mut Option<Path>> {
+        match *self {
+            ErrorImpl::Type { ref mut path, .. }
+            | ErrorImpl::Serde { ref mut path, .. }
+            | ErrorImpl::Range { ref mut path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Displays ` at {path}` for a known location other than the root.
+struct Location<'a>(&'a Option<Path>);
+
+impl<'a> fmt::Display for Location<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Some(ref path) if !path.is_root() => write!(f, " at {}", path),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl fmt::Display for ErrorImpl {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ErrorImpl::PathParse {
+            ErrorImpl::PathParse(ref source) => write!(f, "{}", source),
+
+            ErrorImpl::Range {
+                ref index,
                 ref path,
-                ref source,
-            } => write!(f, "{}\n for {}", source, path),
+            } => write!(f, "invalid range {}{}", index, Location(path)),
 
-            ErrorImpl::Range(ref i) => write!(f, "invalid range {}", i),
+            ErrorImpl::Conflict {
+                ref path,
+                ref found,
+                expected,
+            } => write!(
+                f,
+                "conflict at {}: found {}, expected {}",
+                path, found, expected
+            ),
+
+            ErrorImpl::Missing(ref path) => write!(f, "path {} does not exist", path),
+
+            ErrorImpl::OutOfRange { ref path, len } => write!(
+                f,
+                "index out of range at {}: the array has {} elements",
+                path, len
+            ),
+
+            ErrorImpl::InvalidOption { option, reason } => {
+                write!(f, "invalid option `{}`: {}", option, reason)
+            }
+
+            ErrorImpl::NotSingular(ref path) => {
+                write!(f, "path {} does not address a single location", path)
+            }
 
             ErrorImpl::Type {
                 ref unexpected,
                 expected,
+                ref origin,
+                ref path,
             } => {
-                write!(f, "invalid type: {}, expected {}", unexpected, expected)?;
+                write!(
+                    f,
+                    "invalid type: {}, expected {}{}",
+                    unexpected,
+                    expected,
+                    Location(path)
+                )?;
+                if let Some(ref origin) = *origin {
+                    write!(f, " (from {})", origin)?;
+                }
                 Ok(())
             }
 
             ErrorImpl::Io(ref err) => write!(f, "{}", err),
 
-            ErrorImpl::Serde(ref s) => write!(f, "{}", s),
+            ErrorImpl::Serde {
+                ref message,
+                ref path,
+            } => write!(f, "{}{}", message, Location(path)),
 
             ErrorImpl::FormatParse {
                 ref source,
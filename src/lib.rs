@@ -1,33 +1,47 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 #![allow(unknown_lints)]
+#![forbid(unsafe_code)]
 
 #[macro_use]
 extern crate pest_derive;
 #[macro_use]
 extern crate serde;
 
-pub use error::Error;
-pub use value::to_value;
-pub use value::Value;
+pub use env::Environment;
+pub use error::{Error, ErrorKind, Unexpected};
+pub use path::{
+    CompareOp, Dotted, DottedPath, FilterExpr, Literal, Operand, Path, PathNode, PathParseError,
+    PathSyntax,
+};
+pub use value::{to_value, Map};
+pub use value::{
+    ArrayMerge, Entry, MergeOptions, MergeStrategy, MismatchPolicy, NilMerge, OccupiedEntry,
+    Origin, OutOfRange, SetOptions, SetPolicy, Tracked, VacantEntry, Value,
+};
 
+mod env;
 mod error;
 mod path;
 mod value;
 
 #[cfg(test)]
 mod tests {
-    use crate::{to_value, Value};
+    use std::convert::TryInto;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        to_value, ArrayMerge, Dotted, Entry, Environment, ErrorKind, MergeOptions, MergeStrategy,
+        MismatchPolicy, NilMerge, OutOfRange, SetOptions, SetPolicy, Tracked, Unexpected, Value,
+    };
 
     #[test]
     fn simple_test() {
         let mut value_origin = Value::default();
 
-        assert!(matches!(value_origin.set("/test/bool", true), Ok(_)));
-        assert!(matches!(
-            value_origin.set("/test/str", "i am string"),
-            Ok(_)
-        ));
+        assert!(value_origin.set("/test/bool", true).is_ok());
+        assert!(value_origin.set("/test/str", "i am string").is_ok());
 
         assert!(matches!(value_origin.get("/test/bool"), Ok(Some(true))));
         assert!(
@@ -36,10 +50,10 @@ mod tests {
 
         let mut value_new = Value::default();
 
-        assert!(matches!(value_new.set("/test/bool", false), Ok(_)));
-        assert!(matches!(value_new.set("/test/i32", 1000_i32), Ok(_)));
+        assert!(value_new.set("/test/bool", false).is_ok());
+        assert!(value_new.set("/test/i32", 1000_i32).is_ok());
 
-        assert!(matches!(value_origin.merge(value_new), Ok(_)));
+        assert!(value_origin.merge(value_new).is_ok());
 
         assert!(matches!(value_origin.get("/test/bool"), Ok(Some(false))));
         assert!(
@@ -64,12 +78,905 @@ mod tests {
         );
 
         let value = to_value(str_value);
-        assert!(matches!(to_value(str_value), Ok(_)));
+        assert!(to_value(str_value).is_ok());
 
         let mut value = value.unwrap();
 
         // should override origin value inside the value
-        assert!(matches!(value.set("/test/bool", false), Ok(_)));
+        assert!(value.set("/test/bool", false).is_ok());
         assert!(matches!(value.get("/test/bool"), Ok(Some(false))));
     }
+
+    #[test]
+    fn serde_round_trip_test() {
+        let mut value = Value::default();
+        assert!(value.set("/big", u64::MAX).is_ok());
+        assert!(value.set("/neg", i64::MIN).is_ok());
+        assert!(value.set("/nil", Value::Nil).is_ok());
+        assert!(value.set("/list[0]", 1.5).is_ok());
+
+        let json = serde_json::to_string(&value).unwrap();
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, value);
+        assert!(matches!(parsed.get("/big"), Ok(Some(u64::MAX))));
+
+        assert!(value.set("/big", u128::MAX).is_ok());
+        assert!(value.set("/neg", i128::MIN).is_ok());
+        assert!(matches!(to_value(&value), Ok(v) if v == value));
+        assert!(matches!(Value::deserialize(value.clone()), Ok(v) if v == value));
+    }
+
+    #[test]
+    fn serde_nil_is_not_missing_test() {
+        #[derive(Debug, Deserialize)]
+        struct Holder {
+            value: Value,
+        }
+
+        let holder: Holder = serde_json::from_str(r#"{"value": null}"#).unwrap();
+        assert_eq!(holder.value, Value::Nil);
+        assert!(serde_json::from_str::<Holder>("{}").is_err());
+    }
+
+    #[test]
+    fn to_value_quoted_key_test() {
+        let mut labels = std::collections::HashMap::new();
+        labels.insert("app.kubernetes.io/name", "path-value");
+        labels.insert("with space", "yes");
+
+        let value = to_value(labels).unwrap();
+        assert!(
+            matches!(value.get::<String, _, _>(r#"/"app.kubernetes.io/name""#), Ok(Some(s)) if s == "path-value")
+        );
+        assert!(matches!(value.get::<String, _, _>(r#"/"with space""#), Ok(Some(s)) if s == "yes"));
+    }
+
+    #[test]
+    fn query_test() {
+        let mut value = Value::default();
+        assert!(value.set("/services/web/port", 80).is_ok());
+        assert!(value.set("/services/db/port", 5432).is_ok());
+        assert!(value.set("/services/db/replica/name", "replica").is_ok());
+        assert!(value.set("/name", "root").is_ok());
+
+        let mut ports: Vec<(String, i32)> = value
+            .query("/services/*/port")
+            .unwrap()
+            .map(|(path, v)| (path.to_string(), v.clone().try_into().unwrap()))
+            .collect();
+        ports.sort();
+        assert_eq!(
+            ports,
+            vec![
+                ("/services/db/port".to_string(), 5432),
+                ("/services/web/port".to_string(), 80)
+            ]
+        );
+
+        let mut names: Vec<String> = value
+            .query("/**/name")
+            .unwrap()
+            .map(|(path, _)| path.to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["/name", "/services/db/replica/name"]);
+
+        assert!(matches!(
+            value.get::<i32, _, _>("/services/*/port"),
+            Ok(Some(_))
+        ));
+        assert!(matches!(
+            value.get::<i32, _, _>("/services/db/missing"),
+            Ok(None)
+        ));
+
+        assert!(value.set("/services/*/port", 8080).is_ok());
+        assert_eq!(value.query("/services/*/port").unwrap().count(), 2);
+        assert!(value
+            .query("/services/*/port")
+            .unwrap()
+            .all(|(_, v)| *v == Value::from(8080)));
+    }
+
+    #[test]
+    fn slice_test() {
+        let mut value = Value::default();
+        assert!(value.set("/items", vec![0, 1, 2, 3, 4]).is_ok());
+
+        assert!(matches!(value.get("/items[-1]"), Ok(Some(4))));
+        assert!(matches!(value.get::<i32, _, _>("/items[-6]"), Ok(None)));
+
+        let items: Vec<Value> = value
+            .query("/items[1:-1]")
+            .unwrap()
+            .map(|(_, v)| v.clone())
+            .collect();
+        assert_eq!(items, vec![Value::from(1), Value::from(2), Value::from(3)]);
+
+        let paths: Vec<String> = value
+            .query("/items[::-2]")
+            .unwrap()
+            .map(|(p, _)| p.to_string())
+            .collect();
+        assert_eq!(paths, vec!["/items[4]", "/items[2]", "/items[0]"]);
+
+        let slice = |path: &str| -> Vec<String> {
+            value
+                .query(path)
+                .unwrap()
+                .map(|(p, _)| p.to_string())
+                .collect()
+        };
+        assert_eq!(slice("/items[1::9223372036854775807]"), vec!["/items[1]"]);
+        assert_eq!(slice("/items[3::9223372036854775807]"), vec!["/items[3]"]);
+        assert_eq!(slice("/items[::-9223372036854775808]"), vec!["/items[4]"]);
+        assert_eq!(slice("/items[1::-9223372036854775808]"), vec!["/items[1]"]);
+
+        assert!(
+            matches!(value.set("/items[::2]", 9), Ok(Value::Array(old)) if old == vec![Value::from(0), Value::from(2), Value::from(4)])
+        );
+        assert!(
+            matches!(value.get::<Vec<Value>, _, _>("/items"), Ok(Some(items)) if items == vec![Value::from(9), Value::from(1), Value::from(9), Value::from(3), Value::from(9)])
+        );
+    }
+
+    #[test]
+    fn filter_test() {
+        let mut value = Value::default();
+        assert!(value.set("/listeners[0]/name", "https").is_ok());
+        assert!(value.set("/listeners[0]/port", 443).is_ok());
+        assert!(value.set("/listeners[1]/name", "http").is_ok());
+        assert!(value.set("/listeners[1]/port", 80).is_ok());
+        assert!(value.set("/listeners[1]/enabled", true).is_ok());
+
+        assert!(matches!(
+            value.get(r#"/listeners[?(@.name == "http")]/port"#),
+            Ok(Some(80))
+        ));
+        assert!(matches!(
+            value.get::<i32, _, _>(r#"/listeners[?(@.name == "ftp")]/port"#),
+            Ok(None)
+        ));
+
+        let ports: Vec<Value> = value
+            .query("/listeners[?(@.port > 79.5 && !@.enabled)]/port")
+            .unwrap()
+            .map(|(_, v)| v.clone())
+            .collect();
+        assert_eq!(ports, vec![Value::from(443)]);
+
+        let paths: Vec<String> = value
+            .query(r#"/listeners[?(@.enabled == true || @.name == "https")]"#)
+            .unwrap()
+            .map(|(p, _)| p.to_string())
+            .collect();
+        assert_eq!(paths, vec!["/listeners[0]", "/listeners[1]"]);
+
+        assert!(value.set("/listeners[?(@.enabled)]/port", 8080).is_ok());
+        assert!(matches!(value.get("/listeners[1]/port"), Ok(Some(8080))));
+        assert!(matches!(value.get("/listeners[0]/port"), Ok(Some(443))));
+    }
+
+    #[test]
+    fn entry_test() {
+        let mut value = Value::default();
+
+        assert!(matches!(value.entry("/a/b"), Ok(Entry::Vacant(_))));
+        *value.entry("/a/b").unwrap().or_insert(1) = Value::from(2);
+        assert!(matches!(value.get("/a/b"), Ok(Some(2))));
+
+        value
+            .entry("/a/b")
+            .unwrap()
+            .and_modify(|v| *v = Value::from(3))
+            .or_insert(4);
+        assert!(matches!(value.get("/a/b"), Ok(Some(3))));
+
+        if let Ok(Some(v)) = value.get_mut("/a/b") {
+            *v = Value::from("mutated");
+        }
+        assert!(matches!(value.get::<String, _, _>("/a/b"), Ok(Some(s)) if s == "mutated"));
+        assert!(matches!(value.get_mut("/a/c"), Ok(None)));
+        assert!(matches!(value.get_mut("/*/b"), Ok(Some(_))));
+        assert!(value.entry("/a/*").is_err());
+    }
+
+    #[test]
+    fn set_creates_containers_test() {
+        let mut value = Value::default();
+
+        // out of range indices pad the array with nil instead of panicking
+        assert!(matches!(value.set("/list[2]", 1), Ok(Value::Nil)));
+        assert!(
+            matches!(value.get::<Vec<Value>, _, _>("/list"), Ok(Some(l)) if l == vec![Value::Nil, Value::Nil, Value::from(1)])
+        );
+        assert!(matches!(value.set("/list[-5]", 0), Ok(Value::Nil)));
+        assert!(matches!(value.get::<Vec<Value>, _, _>("/list"), Ok(Some(l)) if l.len() == 5));
+        assert!(matches!(value.get("/list[0]"), Ok(Some(0))));
+
+        assert!(matches!(value.set("/list[-1]", 2), Ok(v) if v == Value::from(1)));
+        assert!(matches!(value.get("/list[4]"), Ok(Some(2))));
+    }
+
+    #[test]
+    fn set_with_policy_test() {
+        let mut value = Value::default();
+        assert!(value.set("/a", 1).is_ok());
+        assert!(value.set("/list", vec![1, 2]).is_ok());
+
+        let strict = SetOptions::new().policy(SetPolicy::ErrorOnConflict);
+        let err = value.set_with("/a/b", 2, &strict).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "conflict at /a: found integer `1`, expected a map"
+        );
+        assert!(value.set_with("/list/b", 2, &strict).is_err());
+        assert!(matches!(value.get("/a"), Ok(Some(1))));
+
+        assert!(value.set_with("/new/b", 2, &strict).is_ok());
+        assert!(value.set_with("/list[0]", 9, &strict).is_ok());
+        assert!(matches!(value.set_with("/a", "scalar", &strict), Ok(v) if v == Value::from(1)));
+
+        let existing = SetOptions::new().policy(SetPolicy::ErrorOnMissing);
+        let err = value.set_with("/x/y/z", 1, &existing).unwrap_err();
+        assert_eq!(err.to_string(), "path /x does not exist");
+        assert!(value.set_with("/new/c", 3, &existing).is_ok());
+        assert!(value.set_with("/list[5]/a", 3, &existing).is_err());
+
+        // the default policy keeps the old behavior
+        assert!(value.set_with("/a/b", 2, &SetOptions::new()).is_ok());
+        assert!(matches!(value.get("/a/b"), Ok(Some(2))));
+    }
+
+    #[test]
+    fn remove_test() {
+        let mut value = Value::default();
+        assert!(value.set("/a/b", 1).is_ok());
+        assert!(value.set("/a/c", 2).is_ok());
+        assert!(value.set("/list", vec![0, 1, 2, 3, 4]).is_ok());
+
+        assert!(matches!(value.remove("/a/b"), Ok(Some(v)) if v == Value::from(1)));
+        assert!(matches!(value.remove("/a/b"), Ok(None)));
+        assert!(matches!(value.get::<i32, _, _>("/a/b"), Ok(None)));
+
+        assert!(matches!(value.take("/a/c"), Ok(Some(v)) if v == Value::from(2)));
+        assert!(matches!(value.get_mut("/a/c"), Ok(Some(Value::Nil))));
+
+        assert!(matches!(value.remove("/list[-1]"), Ok(Some(v)) if v == Value::from(4)));
+        assert!(matches!(value.remove("/list[-9]"), Ok(None)));
+        assert!(
+            matches!(value.remove("/list[::-2]"), Ok(Some(Value::Array(v))) if v == vec![Value::from(3), Value::from(1)])
+        );
+        assert!(
+            matches!(value.get::<Vec<Value>, _, _>("/list"), Ok(Some(l)) if l == vec![Value::from(0), Value::from(2)])
+        );
+    }
+
+    #[test]
+    fn remove_pruned_test() {
+        let mut value = Value::default();
+        assert!(value.set("/a/b/c", 1).is_ok());
+        assert!(value.set("/a/d", 2).is_ok());
+
+        assert!(matches!(value.remove_pruned("/a/b/c"), Ok(Some(_))));
+        assert!(matches!(value.get_mut("/a/b"), Ok(None)));
+        assert!(matches!(value.get("/a/d"), Ok(Some(2))));
+
+        assert!(matches!(value.remove_pruned("/a/d"), Ok(Some(_))));
+        assert!(matches!(value.get_mut("/a"), Ok(None)));
+        assert!(matches!(value, Value::Map(ref m) if m.is_empty()));
+
+        let mut value = Value::default();
+        assert!(value.set("/x/list[1]/y", Value::Nil).is_ok());
+        assert!(value.set("/z", 1).is_ok());
+        value.retain(|_, v| *v != Value::Nil && !v.is_empty_container());
+        assert!(matches!(value.get_mut("/x"), Ok(None)));
+        assert!(matches!(value.get("/z"), Ok(Some(1))));
+    }
+
+    #[test]
+    fn merge_with_test() {
+        let mut base = Value::default();
+        assert!(base.set("/list", vec![1, 2]).is_ok());
+        assert!(base.set("/tags", vec![1, 2]).is_ok());
+        assert!(base.set("/db/host", "localhost").is_ok());
+        assert!(base.set("/db/port", 5432).is_ok());
+        assert!(base.set("/debug", true).is_ok());
+
+        let mut layer = Value::default();
+        assert!(layer.set("/list", vec![3]).is_ok());
+        assert!(layer.set("/tags", vec![3]).is_ok());
+        assert!(layer.set("/db/host", Value::Nil).is_ok());
+        assert!(layer.set("/db/port", "5433").is_ok());
+        assert!(layer.set("/debug", Value::Nil).is_ok());
+
+        // The default policy rejects the string port.
+        let err = base.clone().merge(layer.clone()).unwrap_err();
+        assert_eq!(err.expected(), Some("an integer"));
+
+        let options = MergeOptions::new()
+            .arrays(ArrayMerge::Append)
+            .nil(NilMerge::Delete)
+            .mismatch(MismatchPolicy::SourceWins)
+            .override_at(
+                "/tags".try_into().unwrap(),
+                MergeStrategy::new().arrays(ArrayMerge::Replace),
+            );
+        assert!(base.merge_with(layer, &options).is_ok());
+        assert!(matches!(base.get("/list[2]"), Ok(Some(3))));
+        assert!(
+            matches!(base.get::<Vec<Value>, _, _>("/tags"), Ok(Some(t)) if t == vec![Value::from(3)])
+        );
+        assert!(matches!(base.get::<String, _, _>("/db/host"), Ok(None)));
+        assert!(matches!(base.get::<String, _, _>("/db/port"), Ok(Some(p)) if p == "5433"));
+        assert!(matches!(base.get::<bool, _, _>("/debug"), Ok(None)));
+    }
+
+    #[test]
+    fn borrowed_get_test() {
+        let mut value = Value::default();
+        assert!(value.set("/name", "path-value").is_ok());
+        assert!(value.set("/ports", vec![80, 443]).is_ok());
+        assert!(value.set("/db/host", "localhost").is_ok());
+
+        assert!(matches!(value.get_str("/name"), Ok(Some("path-value"))));
+        assert!(matches!(value.get_str("/missing"), Ok(None)));
+        assert!(value.get_str("/ports").is_err());
+        assert!(matches!(value.get_ref("/ports[1]"), Ok(Some(v)) if *v == Value::from(443)));
+
+        let ports: &[Value] = value.get_borrowed("/ports").unwrap().unwrap();
+        assert_eq!(ports.len(), 2);
+        let port: u16 = value.get_borrowed("/ports[0]").unwrap().unwrap();
+        assert_eq!(port, 80);
+
+        let db = value.get_ref("/db").unwrap().unwrap();
+        assert!(matches!(db.as_map(), Some(m) if m.len() == 1));
+        assert!(db.as_array().is_none());
+        assert_eq!(db.as_map().unwrap()["host"].as_str(), Some("localhost"));
+    }
+
+    #[test]
+    fn get_as_set_from_test() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct DatabaseConfig {
+            host: String,
+            port: u16,
+            replicas: Vec<String>,
+        }
+
+        let config = DatabaseConfig {
+            host: "localhost".to_string(),
+            port: 5432,
+            replicas: vec!["r1".to_string(), "r2".to_string()],
+        };
+
+        let mut value = Value::default();
+        assert!(value.set_from("/services/db", &config).is_ok());
+        assert!(matches!(value.get("/services/db/port"), Ok(Some(5432))));
+
+        let loaded: Option<DatabaseConfig> = value.get_as("/services/db").unwrap();
+        assert_eq!(loaded, Some(config));
+        assert!(matches!(
+            value.get_as::<DatabaseConfig, _, _>("/services/cache"),
+            Ok(None)
+        ));
+        assert!(value
+            .get_as::<DatabaseConfig, _, _>("/services/db/host")
+            .is_err());
+    }
+
+    #[test]
+    fn nested_array_test() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let value = to_value(&grid).unwrap();
+        assert!(matches!(value.get("/[1][2]"), Ok(Some(6))));
+        assert!(matches!(value.get("/[-1][0]"), Ok(Some(4))));
+
+        let mut value = Value::default();
+        assert!(value.set_from("/grid", &grid).is_ok());
+        assert!(value.set("/grid[0][1]", 20).is_ok());
+        assert!(matches!(value.get("/grid[0][1]"), Ok(Some(20))));
+        assert!(
+            matches!(value.get_as::<Vec<Vec<i32>>, _, _>("/grid"), Ok(Some(g)) if g == vec![vec![1, 20, 3], vec![4, 5, 6]])
+        );
+
+        let empty: Vec<Vec<i32>> = vec![vec![]];
+        assert!(
+            matches!(to_value(&empty), Ok(Value::Array(ref a)) if a == &vec![Value::Array(vec![])])
+        );
+    }
+
+    #[test]
+    fn error_path_test() {
+        #[derive(Debug, Deserialize)]
+        struct DatabaseConfig {
+            #[allow(dead_code)]
+            replicas: Vec<u16>,
+        }
+
+        let mut value = Value::default();
+        assert!(value.set("/db/port", "abc").is_ok());
+        assert!(value.set("/db/replicas", vec![1, 2]).is_ok());
+        assert!(value.set("/db/replicas[1]", "x").is_ok());
+        assert!(value.set("/db/big", 1u64 << 40).is_ok());
+
+        let err = value.get::<i64, _, _>("/db/port").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid type: string \"abc\", expected an integer at /db/port"
+        );
+        let err = value.get::<i32, _, _>("/*/big").unwrap_err();
+        assert!(err.to_string().ends_with(" at /db/big"));
+        let err = value.get_as::<DatabaseConfig, _, _>("/db").unwrap_err();
+        assert!(err.to_string().ends_with(" at /db/replicas[1]"));
+
+        let mut source = Value::default();
+        assert!(source.set("/db/replicas", "none").is_ok());
+        let err = value.merge(source).unwrap_err();
+        assert!(err.to_string().ends_with(" at /db/replicas"));
+    }
+
+    #[test]
+    fn error_kind_test() {
+        let mut value = Value::default();
+        assert!(value.set("/db/port", "abc").is_ok());
+
+        let err = value.get::<i64, _, _>("/db/port").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Type);
+        assert!(err.is_type() && !err.is_range());
+        assert_eq!(
+            err.path().map(|path| path.to_string()).as_deref(),
+            Some("/db/port")
+        );
+        assert_eq!(err.unexpected(), Some(&Unexpected::Str("abc".to_string())));
+        assert_eq!(err.expected(), Some("an integer"));
+
+        let err = value.get_ref("/db[").unwrap_err();
+        assert!(err.is_path_parse());
+        assert!(err.path().is_none() && err.unexpected().is_none());
+
+        let options = SetOptions::new().policy(SetPolicy::ErrorOnConflict);
+        let err = value.set_with("/db/port/x", 1, &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Conflict);
+        assert_eq!(err.expected(), Some("a map"));
+        assert!(value.remove_pruned("/db/*").unwrap_err().is_not_singular());
+    }
+
+    #[test]
+    fn json_pointer_test() {
+        let mut value = Value::default();
+        assert!(value.set("/a[1]/\"b/c\"", 1).is_ok());
+        assert!(value.set("/m/\"0\"", "zero").is_ok());
+
+        assert!(matches!(value.pointer("/a/1/b~1c"), Ok(Some(v)) if *v == Value::from(1)));
+        assert!(matches!(value.pointer("/m/0"), Ok(Some(v)) if v.as_str() == Some("zero")));
+        assert!(matches!(value.pointer("/a/2"), Ok(None)));
+        assert!(matches!(value.pointer("/a/01"), Ok(None)));
+        assert!(matches!(value.pointer(""), Ok(Some(v)) if *v == value));
+        assert!(value.pointer("a").is_err());
+
+        if let Ok(Some(v)) = value.pointer_mut("/a/1/b~1c") {
+            *v = Value::from(2);
+        }
+        assert!(matches!(value.get("/a[1]/\"b/c\""), Ok(Some(2))));
+    }
+
+    #[test]
+    fn dotted_path_test() {
+        let mut value = Value::default();
+        assert!(value.set(Dotted("services.db.hosts[1]"), "db-2").is_ok());
+        assert!(
+            matches!(value.get::<String, _, _>("/services/db/hosts[1]"), Ok(Some(h)) if h == "db-2")
+        );
+        assert!(matches!(
+            value.get_str(Dotted("services.db.hosts[-1]")),
+            Ok(Some("db-2"))
+        ));
+        assert!(value.get_str(Dotted("services..db")).is_err());
+    }
+
+    #[test]
+    fn append_insert_test() {
+        let mut value = Value::default();
+        assert!(value.set("/list[-]", 1).is_ok());
+        assert!(value.set("/list[-]", 3).is_ok());
+        assert!(value.set("/list[^1]", 2).is_ok());
+        assert!(value.set("/list[^0]", 0).is_ok());
+        assert!(value.set("/list[-]/name", "last").is_ok());
+        assert!(matches!(value.get::<Vec<Value>, _, _>("/list"), Ok(Some(ref l)) if l.len() == 5));
+        assert!(matches!(value.get("/list[2]"), Ok(Some(2))));
+        assert!(matches!(value.get_str("/list[-1]/name"), Ok(Some("last"))));
+        assert!(matches!(value.get::<i32, _, _>("/list[-]"), Ok(None)));
+
+        // Out-of-range indices pad with `Nil` by default.
+        let mut value = Value::from(vec![1, 2]);
+        assert!(value.set("/[4]", 5).is_ok());
+        assert!(
+            matches!(value.get::<Vec<Value>, _, _>("/"), Ok(Some(ref l)) if l.len() == 5 && l[2] == Value::Nil)
+        );
+
+        let strict = SetOptions::new().out_of_range(OutOfRange::Error);
+        let mut value = Value::from(vec![1, 2]);
+        assert!(value.set_with("/[2]", 3, &strict).is_ok());
+        assert!(value.set_with("/[^3]", 4, &strict).is_ok());
+        assert!(value.set_with("/[-4]", 0, &strict).is_ok());
+        let err = value.set_with("/[9]", 9, &strict).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "index out of range at /[9]: the array has 4 elements"
+        );
+        assert!(value.set_with("/[-5]", 9, &strict).is_err());
+        assert!(value.set_with("/[^5]", 9, &strict).is_err());
+        assert!(value.set_with("/new[1]", 9, &strict).is_err());
+        assert_eq!(value, Value::from(vec![0, 2, 3, 4]));
+
+        // Even padding has a limit, instead of allocating whatever the index asks.
+        let mut value = Value::default();
+        let err = value.set("/l[1000000000000]", 1).unwrap_err();
+        assert!(err.is_out_of_range());
+        assert!(value
+            .set("/l[-1000000000000]", 1)
+            .unwrap_err()
+            .is_out_of_range());
+        assert!(value
+            .set("/l[^-1000000000000]", 1)
+            .unwrap_err()
+            .is_out_of_range());
+        assert!(value.entry("/l[1000000000000]").is_err());
+        assert_eq!(value, Value::default());
+        assert!(value.set("/l[1000]", 1).is_ok());
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn preserve_order_test() {
+        let mut value = Value::default();
+        for key in &["zeta", "alpha", "mid", "beta"] {
+            assert!(value.set(format!("/{}", key).as_str(), 1).is_ok());
+        }
+        let keys = |value: &Value| value.as_map().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&value), vec!["zeta", "alpha", "mid", "beta"]);
+
+        assert!(value.remove("/alpha").is_ok());
+        let mut layer = Value::default();
+        assert!(layer.set("/omega", 2).is_ok());
+        assert!(layer.set("/zeta", 3).is_ok());
+        assert!(value.merge(layer).is_ok());
+        assert_eq!(keys(&value), vec!["zeta", "mid", "beta", "omega"]);
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"zeta":3,"mid":1,"beta":1,"omega":2}"#);
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(keys(&parsed), keys(&value));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_test() {
+        let json = r#"{"name":"app","big":123456789012345678901234567890,"ratio":0.5,"tags":["a",null,true],"nested":{"x":-1}}"#;
+        let value = Value::from_json_str(json).unwrap();
+        assert!(matches!(value.get_str("/name"), Ok(Some("app"))));
+        assert!(matches!(value.get::<f64, _, _>("/ratio"), Ok(Some(r)) if r == 0.5));
+        assert!(matches!(value.get("/nested/x"), Ok(Some(-1))));
+        assert!(
+            matches!(value.get_ref("/big"), Ok(Some(Value::Integer(i))) if i.to_string() == "123456789012345678901234567890")
+        );
+        assert!(matches!(value.get_ref("/tags[1]"), Ok(Some(Value::Nil))));
+
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(value.to_json_string().unwrap(), json);
+        assert_eq!(
+            Value::from_json_str(&value.to_json_string().unwrap()).unwrap(),
+            value
+        );
+        let reparsed = Value::from_json_reader(value.to_json_pretty().unwrap().as_bytes()).unwrap();
+        assert_eq!(reparsed, value);
+
+        let err = Value::from_json_str("{\n  \"a\": x\n}").unwrap_err();
+        assert!(err.to_string().contains("line 2 column 8"), "{}", err);
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(Value::from(f64::NAN).to_json_string().is_err());
+        assert!(Value::from(f64::INFINITY).to_json_string().is_err());
+
+        // Integers past 128 bits are kept both ways, floats past f64 are errors.
+        let json = r#"[1e2, -340282366920938463463374607431768211457]"#;
+        let value = Value::from_json_str(&format!(" {}\n", json)).unwrap();
+        assert!(matches!(value.get::<f64, _, _>("/[0]"), Ok(Some(r)) if r == 100.0));
+        assert_eq!(
+            value.to_json_string().unwrap(),
+            "[100.0,-340282366920938463463374607431768211457]"
+        );
+        let err = Value::from_json_str("{\n  \"a\": [1e400]\n}").unwrap_err();
+        assert!(err.is_format_parse());
+        assert!(
+            err.to_string().contains("out of range at line 2 column 9"),
+            "{}",
+            err
+        );
+
+        let value =
+            Value::from_json_str(" { \"a\\\"b\" : [ ] , \"c\\\\\" :{ },\"d\":[ 1 , \"x\" ] } ")
+                .unwrap();
+        let map = value.as_map().unwrap();
+        assert_eq!(map.get("a\"b"), Some(&Value::Array(vec![])));
+        assert_eq!(map.get("c\\"), Some(&Value::Map(Default::default())));
+        assert_eq!(
+            map.get("d"),
+            Some(&Value::from(vec![Value::from(1), Value::from("x")]))
+        );
+
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Value::from_json_str(&nested(128)).is_ok());
+        let err = Value::from_json_str(&nested(129)).unwrap_err();
+        assert!(err.is_format_parse());
+        assert!(
+            err.to_string().contains("recursion limit exceeded"),
+            "{}",
+            err
+        );
+        assert!(Value::from_json_str(&nested(100_000))
+            .unwrap_err()
+            .is_format_parse());
+
+        // The `json` feature does not change how serde_json itself behaves.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Number {
+            Float(f64),
+        }
+        assert!(serde_json::from_str::<Number>("1.5").is_ok());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>("1.50").unwrap(),
+            serde_json::json!(1.5)
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_test() {
+        let yaml = "\
+defaults: &defaults
+  replicas: 2
+  image: app
+web:
+  <<: *defaults
+  port: 8080
+worker: *defaults
+big: 170141183460469231731687303715884105727
+tagged: !Secret token
+";
+        let value = Value::from_yaml_str(yaml).unwrap();
+        assert!(matches!(value.get("/worker/replicas"), Ok(Some(2))));
+        assert!(matches!(value.get("/big"), Ok(Some(i128::MAX))));
+        assert!(matches!(value.get_str("/tagged/Secret"), Ok(Some("token"))));
+        assert!(matches!(value.get("/web/port"), Ok(Some(8080))));
+        assert!(matches!(value.get("/web/replicas"), Ok(Some(2))));
+        assert!(matches!(value.get_ref("/web/\"<<\""), Ok(None)));
+
+        let reparsed = Value::from_yaml_str(&value.to_yaml_string().unwrap()).unwrap();
+        assert_eq!(reparsed, value);
+
+        let documents = Value::from_yaml_documents("a: 1\n---\nb: [x, y]\n").unwrap();
+        assert_eq!(documents.len(), 2);
+        assert!(matches!(documents[1].get_str("/b[1]"), Ok(Some("y"))));
+        let stream = Value::to_yaml_documents(&documents).unwrap();
+        assert_eq!(Value::from_yaml_documents(&stream).unwrap(), documents);
+
+        assert!(Value::from_yaml_str("a: 1\n---\nb: 2\n").is_err());
+
+        // Own keys win over merged ones, earlier merged maps over later ones.
+        let merged = Value::from_yaml_str(
+            "a: &a {x: 1, y: 1}\nb: &b {y: 2, z: 2}\nc: {x: 3, <<: [*a, *b]}\n",
+        )
+        .unwrap();
+        assert!(matches!(merged.get("/c/x"), Ok(Some(3))));
+        assert!(matches!(merged.get("/c/y"), Ok(Some(1))));
+        assert!(matches!(merged.get("/c/z"), Ok(Some(2))));
+        assert!(Value::from_yaml_str("a: {<<: 1}\n").is_err());
+
+        let err = Value::from_yaml_str("~: a\n").unwrap_err();
+        assert!(err.to_string().contains("unsupported map key"), "{}", err);
+        let err = Value::from_yaml_str("a: [1, 2\nb: 3\n").unwrap_err();
+        assert!(err.to_string().contains("line"), "{}", err);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_test() {
+        let toml = r#"
+name = "service"
+released = 1979-05-27T07:32:00Z
+
+[database]
+ports = [8000, 8001]
+inline = { a = 1 }
+
+[[servers]]
+host = "alpha"
+
+[[servers]]
+host = "beta"
+"#;
+        let value = Value::from_toml_str(toml).unwrap();
+        assert!(matches!(
+            value.get_str("/released"),
+            Ok(Some("1979-05-27T07:32:00Z"))
+        ));
+        assert!(matches!(value.get("/database/ports[1]"), Ok(Some(8001))));
+        assert!(matches!(
+            value.get_str("/servers[1]/host"),
+            Ok(Some("beta"))
+        ));
+
+        let mut layer = Value::default();
+        assert!(layer.set("/database/user", "admin").is_ok());
+        assert!(layer.set("/database/password", Value::Nil).is_ok());
+        let mut merged = value.clone();
+        assert!(merged.merge(layer).is_ok());
+
+        let output = merged.to_toml_string().unwrap();
+        assert!(output.contains("[[servers]]"), "{}", output);
+        assert!(output.contains("[database]"), "{}", output);
+        assert!(!output.contains("password"), "{}", output);
+        let reparsed = Value::from_toml_str(&output).unwrap();
+        assert!(matches!(
+            reparsed.get_str("/database/user"),
+            Ok(Some("admin"))
+        ));
+        assert!(matches!(reparsed.get("/database/inline/a"), Ok(Some(1))));
+
+        assert!(Value::from(1).to_toml_string().is_err());
+        assert!(Value::from_toml_str("a = ").is_err());
+        let mut big = Value::default();
+        assert!(big.set("/n", u64::MAX).is_ok());
+        assert!(big.to_toml_string().is_err());
+    }
+
+    #[test]
+    fn environment_test() {
+        let env = Environment::with_prefix("APP").source(vec![
+            ("APP__DB__HOST", "db.local"),
+            ("APP__DB__PORT", "5432"),
+            ("APP__HOSTS__0", "a"),
+            ("APP__HOSTS__1", "b"),
+            ("APP__DEBUG", "true"),
+            ("APP__RATIO", "0.25"),
+            ("APP__ZIP", "00501"),
+            ("APP__COUNT", "1_000"),
+            ("APP__BIG", "123456789012345678901234567890"),
+            ("APP__", "ignored"),
+            ("APP__A____B", "ignored"),
+            ("OTHER__X", "ignored"),
+            ("APPX__Y", "ignored"),
+        ]);
+        let overlay = env.load().unwrap();
+        assert!(matches!(overlay.get_str("/db/host"), Ok(Some("db.local"))));
+        assert!(matches!(overlay.get("/db/port"), Ok(Some(5432))));
+        assert!(matches!(overlay.get_str("/hosts[1]"), Ok(Some("b"))));
+        assert!(matches!(
+            overlay.get_ref("/debug"),
+            Ok(Some(Value::Boolean(true)))
+        ));
+        assert!(matches!(overlay.get_ref("/ratio"), Ok(Some(Value::Float(r))) if *r == 0.25));
+        assert!(matches!(overlay.get_str("/zip"), Ok(Some("00501"))));
+        assert!(matches!(overlay.get_str("/count"), Ok(Some("1_000"))));
+        assert!(matches!(
+            overlay.get_ref("/big"),
+            Ok(Some(Value::Integer(_)))
+        ));
+        assert_eq!(overlay.as_map().unwrap().len(), 7);
+
+        let mut config = Value::default();
+        assert!(config.set("/db/host", "localhost").is_ok());
+        assert!(config.set("/db/user", "admin").is_ok());
+        assert!(config.merge(overlay).is_ok());
+        assert!(matches!(config.get_str("/db/host"), Ok(Some("db.local"))));
+        assert!(matches!(config.get_str("/db/user"), Ok(Some("admin"))));
+
+        let env = Environment::new()
+            .separator("_")
+            .lowercase(false)
+            .parse_values(false)
+            .source(vec![("Db_Port", "5432")]);
+        assert!(matches!(
+            env.load().unwrap().get_ref("/Db/Port"),
+            Ok(Some(Value::String(_)))
+        ));
+
+        let env =
+            Environment::with_prefix("APP").source(vec![("APP__DB", "x"), ("APP__DB__HOST", "y")]);
+        assert!(env.load().is_err());
+
+        let hosts: Vec<(String, String)> = (0..11)
+            .map(|i| (format!("APP__HOSTS__{}", i), i.to_string()))
+            .collect();
+        let overlay = Environment::with_prefix("APP")
+            .source(hosts)
+            .load()
+            .unwrap();
+        assert!(matches!(overlay.get("/hosts[10]"), Ok(Some(10))));
+
+        let env = Environment::with_prefix("APP").source(vec![("APP__HOSTS__99999999999", "x")]);
+        assert!(env.load().unwrap_err().is_out_of_range());
+        let env = Environment::with_prefix("APP")
+            .source(vec![("APP__HOSTS__99999999999999999999999", "x")]);
+        assert!(env.load().unwrap_err().is_range());
+
+        let env = Environment::with_prefix("APP").source(vec![("APP__HOSTS__00", "x")]);
+        assert!(matches!(
+            env.load().unwrap().get_str(r#"/hosts/"00""#),
+            Ok(Some("x"))
+        ));
+
+        let env = Environment::with_prefix("APP")
+            .separator("")
+            .source(vec![("APP__DB", "x")]);
+        assert_eq!(env.load().unwrap_err().kind(), ErrorKind::InvalidOption);
+        assert!(env.path_of("APP__DB").unwrap_err().is_invalid_option());
+    }
+
+    #[test]
+    fn tracked_test() {
+        let mut defaults = Value::default();
+        assert!(defaults.set("/db/host", "localhost").is_ok());
+        assert!(defaults.set("/db/port", "5432").is_ok());
+        assert!(defaults.set("/hosts", vec!["a", "b"]).is_ok());
+        let mut config = Tracked::new(defaults, "defaults.yaml");
+
+        let mut file = Value::default();
+        assert!(file.set("/db/host", "db.local").is_ok());
+        assert!(file.set("/db/port", "not a port").is_ok());
+        assert!(config.merge(Tracked::new(file, "config.yaml")).is_ok());
+
+        let origin = |config: &Tracked, path: &str| {
+            config
+                .origin(path)
+                .unwrap()
+                .map(|origin| origin.to_string())
+        };
+        assert_eq!(origin(&config, "/db/host").as_deref(), Some("config.yaml"));
+        assert_eq!(origin(&config, "/db").as_deref(), Some("defaults.yaml"));
+        assert_eq!(
+            origin(&config, "/hosts[1]").as_deref(),
+            Some("defaults.yaml")
+        );
+        assert_eq!(origin(&config, "/missing"), None);
+
+        let err = config.get::<i64, _, _>("/db/port").unwrap_err();
+        assert!(err.to_string().ends_with("(from config.yaml)"));
+
+        assert!(config.set("/hosts[^0]", "z", "env").is_ok());
+        assert_eq!(origin(&config, "/hosts[0]").as_deref(), Some("env"));
+        assert_eq!(
+            origin(&config, "/hosts[2]").as_deref(),
+            Some("defaults.yaml")
+        );
+        assert!(config.set("/hosts[-]", "c", "cli").is_ok());
+        assert_eq!(origin(&config, "/hosts[-1]").as_deref(), Some("cli"));
+        assert!(config.set("/hosts[^-1000000000000]", "y", "cli").is_err());
+        assert_eq!(origin(&config, "/hosts[0]").as_deref(), Some("env"));
+
+        let mut overlay = Value::default();
+        assert!(overlay.set("/hosts", vec!["x"]).is_ok());
+        let untracked = Tracked::from(overlay);
+        let options = MergeOptions::new().arrays(ArrayMerge::Append);
+        assert!(config.merge_with(untracked, &options).is_ok());
+        assert_eq!(origin(&config, "/hosts[4]"), None);
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn miette_test() {
+        use miette::Diagnostic;
+
+        let err = Value::default().get_ref("/db(0)").unwrap_err();
+        let label = err.labels().unwrap().next().unwrap();
+        assert_eq!(label.offset(), 3);
+        assert_eq!(label.label(), Some("unexpected `(`, expected end of path"));
+        assert_eq!(
+            err.help().map(|help| help.to_string()).as_deref(),
+            Some("did you mean `/db[0]`?")
+        );
+        assert!(err.source_code().is_some());
+    }
 }
@@ -0,0 +1,258 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::ops::Range;
+
+use pest::error::{ErrorVariant, InputLocation};
+use pest::Parser;
+
+use super::parser::{PathParser, Rule};
+use super::PathSyntax;
+
+/// A path that could not be parsed, with the location of the problem.
+///
+/// `Display` renders a snippet of the path with a caret under the offending
+/// part, followed by a hint if one could be guessed:
+///
+/// ```text
+/// invalid path `/db(0)`
+///   |
+///   | /db(0)
+///   |    ^ unexpected `(`, expected end of path
+///   |
+///   = help: did you mean `/db[0]`?
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathParseError {
+    path: String,
+    span: Range<usize>,
+    message: String,
+    expected: Vec<String>,
+    hint: Option<String>,
+}
+
+impl PathParseError {
+    /// Creates an error about the bytes `span` of `path`.
+    pub(crate) fn new<M: Into<String>>(path: &str, span: Range<usize>, message: M) -> Self {
+        PathParseError {
+            path: path.to_string(),
+            span,
+            message: message.into(),
+            expected: Vec::new(),
+            hint: None,
+        }
+    }
+
+    /// Converts a pest error from parsing `path` in `syntax`.
+    pub(crate) fn from_pest(
+        error: pest::error::Error<Rule>,
+        path: &str,
+        syntax: PathSyntax,
+    ) -> Self {
+        let span = match error.location {
+            InputLocation::Pos(pos) => pos..pos,
+            InputLocation::Span((start, end)) => start..end,
+        };
+        let mut result = match error.variant {
+            ErrorVariant::CustomError { message } => PathParseError::new(path, span, message),
+            ErrorVariant::ParsingError { positives, .. } => {
+                let mut expected: Vec<String> = Vec::new();
+                for rule in positives {
+                    let name = describe(rule);
+                    if !expected.contains(&name) {
+                        expected.push(name);
+                    }
+                }
+                let found = match path[span.start..].chars().next() {
+                    Some(c) => format!("unexpected `{}`", c),
+                    None => "unexpected end of path".to_string(),
+                };
+                let message = if expected.is_empty() {
+                    found
+                } else {
+                    format!("{}, expected {}", found, join(&expected))
+                };
+                PathParseError {
+                    expected,
+                    ..PathParseError::new(path, span, message)
+                }
+            }
+        };
+        result.hint =
+            suggest(path, result.span.start, syntax).map(|s| format!("did you mean `{}`?", s));
+        result
+    }
+
+    /// The path that failed to parse.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The byte offset of the problem in the path.
+    pub fn offset(&self) -> usize {
+        self.span.start
+    }
+
+    /// The bytes of the path the problem is about. Empty when it is about a
+    /// position, e.g. the end of the path.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// What went wrong, e.g. ``unexpected `(`, expected end of path``.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The tokens that would have been accepted at the offset, e.g. `an index`.
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// A suggested fix, e.g. ``did you mean `/db[0]`?``.
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+}
+
+impl fmt::Display for PathParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Columns are counted in characters so the caret lines up with
+        // non-ASCII keys.
+        let column = self.path[..self.span.start].chars().count();
+        let width = self.path[self.span.clone()].chars().count().max(1);
+        writeln!(f, "invalid path `{}`", self.path)?;
+        writeln!(f, "  |")?;
+        writeln!(f, "  | {}", self.path)?;
+        write!(
+            f,
+            "  | {}{} {}",
+            " ".repeat(column),
+            "^".repeat(width),
+            self.message
+        )?;
+        if let Some(ref hint) = self.hint {
+            write!(f, "\n  |\n  = help: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for PathParseError {}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for PathParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("path_value::path_parse"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.hint
+            .as_ref()
+            .map(|hint| Box::new(hint) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.path)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let label =
+            miette::LabeledSpan::new(Some(self.message.clone()), self.span.start, self.span.len());
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+/// Names a grammar rule the way a user would write it.
+fn describe(rule: Rule) -> String {
+    let name = match rule {
+        Rule::EOI => "end of path",
+        Rule::sub_paths => "`/`",
+        Rule::sub_path | Rule::dotted_sub_paths | Rule::path_ident => "a key",
+        Rule::quoted_ident => "a quoted key",
+        Rule::index => "an index",
+        Rule::slice | Rule::slice_start | Rule::slice_end | Rule::slice_step => "a slice",
+        Rule::filter => "a filter",
+        Rule::append => "`-`",
+        Rule::insert_before => "`^`",
+        Rule::filter_path => "`@`",
+        Rule::number => "a number",
+        Rule::string => "a string",
+        Rule::boolean => "`true` or `false`",
+        Rule::nil => "`nil`",
+        Rule::compare_op => "a comparison operator",
+        rule => return format!("{:?}", rule),
+    };
+    name.to_string()
+}
+
+/// Joins `items` as `a, b or c`.
+fn join(items: &[String]) -> String {
+    match items.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// Guesses a fix for a path that failed to parse at `offset`. Only guesses
+/// that parse are returned.
+fn suggest(path: &str, offset: usize, syntax: PathSyntax) -> Option<String> {
+    let rule = match syntax {
+        PathSyntax::Slash => Rule::path,
+        PathSyntax::Dotted => Rule::dotted_path,
+    };
+    let parses = |candidate: &str| PathParser::parse(rule, candidate).is_ok();
+    let rest = &path[offset..];
+
+    // `(0)` written for `[0]`.
+    if rest.starts_with('(') {
+        if let Some(close) = rest.find(')') {
+            let candidate = format!(
+                "{}[{}]{}",
+                &path[..offset],
+                &rest[1..close],
+                &rest[close + 1..]
+            );
+            if parses(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+
+    match syntax {
+        PathSyntax::Slash if !path.starts_with('/') => {
+            // A dotted path, or a leading `/` left off.
+            if PathParser::parse(Rule::dotted_path, path).is_ok() {
+                if let Ok(dotted) = PathParser::parse_dotted_to_path(path) {
+                    return Some(dotted.to_string());
+                }
+            }
+            let candidate = format!("/{}", path);
+            if parses(&candidate) {
+                return Some(candidate);
+            }
+        }
+        PathSyntax::Slash => {
+            // A key with characters that need quoting, e.g. `/a b`.
+            let start = path[..offset].rfind('/').map_or(0, |i| i + 1);
+            let end = rest.find(['/', '[']).map_or(path.len(), |i| offset + i);
+            let key = &path[start..end];
+            let in_selector = path[start..offset].contains('[');
+            if !key.is_empty() && !in_selector && !key.contains('"') {
+                let candidate = format!("{}\"{}\"{}", &path[..start], key, &path[end..]);
+                if parses(&candidate) {
+                    return Some(candidate);
+                }
+            }
+        }
+        PathSyntax::Dotted if path.starts_with('/') => {
+            if PathParser::parse(Rule::path, path).is_ok() {
+                if let Ok(slash) = PathParser::parse_to_path(path) {
+                    return Some(slash.dotted().to_string());
+                }
+            }
+        }
+        PathSyntax::Dotted => {}
+    }
+    None
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use num_bigint::BigInt;
+
+use super::{write_key, write_quoted, Path, PathNode};
+
+/// A predicate of a filter selector, e.g. `@.enabled == true && @.port > 1024`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum FilterExpr {
+    /// `left || right`
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    /// `left && right`
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    /// `!expr`
+    Not(Box<FilterExpr>),
+    /// `@.path`, true if the path exists in the current element.
+    Exists(Path),
+    /// `left op right`
+    Compare(Operand, CompareOp, Operand),
+}
+
+/// One side of a [`FilterExpr::Compare`].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum Operand {
+    /// A path relative to the current element, written `@`, `@.key` or `@[0]`.
+    Current(Path),
+    /// A constant.
+    Literal(Literal),
+}
+
+/// Comparison operators of a [`FilterExpr::Compare`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A constant in a filter expression.
+#[derive(Debug, Clone)]
+pub enum Literal {
+    Nil,
+    Boolean(bool),
+    Integer(BigInt),
+    Float(f64),
+    String(String),
+}
+
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Nil, Literal::Nil) => true,
+            (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
+            (Literal::Integer(a), Literal::Integer(b)) => a == b,
+            // Compare bit patterns so that `Literal` can be `Eq` and `Hash`.
+            (Literal::Float(a), Literal::Float(b)) => a.to_bits() == b.to_bits(),
+            (Literal::String(a), Literal::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Literal {}
+
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match *self {
+            Literal::Nil => {}
+            Literal::Boolean(b) => b.hash(state),
+            Literal::Integer(ref i) => i.hash(state),
+            Literal::Float(f) => f.to_bits().hash(state),
+            Literal::String(ref s) => s.hash(state),
+        }
+    }
+}
+
+impl FilterExpr {
+    fn is_binary(&self) -> bool {
+        matches!(
+            *self,
+            FilterExpr::Or(..) | FilterExpr::And(..) | FilterExpr::Compare(..)
+        )
+    }
+}
+
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FilterExpr::Or(ref left, ref right) => write!(f, "{} || {}", left, right),
+            FilterExpr::And(ref left, ref right) => {
+                for (i, side) in [left, right].iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" && ")?;
+                    }
+                    if let FilterExpr::Or(..) = ***side {
+                        write!(f, "({})", side)?;
+                    } else {
+                        write!(f, "{}", side)?;
+                    }
+                }
+                Ok(())
+            }
+            FilterExpr::Not(ref expr) if expr.is_binary() => write!(f, "!({})", expr),
+            FilterExpr::Not(ref expr) => write!(f, "!{}", expr),
+            FilterExpr::Exists(ref path) => write_current(f, path),
+            FilterExpr::Compare(ref left, op, ref right) => {
+                write!(f, "{} {} {}", left, op, right)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Current(ref path) => write_current(f, path),
+            Operand::Literal(ref literal) => write!(f, "{}", literal),
+        }
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        })
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Literal::Nil => f.write_str("nil"),
+            Literal::Boolean(b) => write!(f, "{}", b),
+            Literal::Integer(ref i) => write!(f, "{}", i),
+            // Debug output always keeps a fraction or exponent, so the
+            // literal parses back as a float.
+            Literal::Float(v) => write!(f, "{:?}", v),
+            Literal::String(ref s) => write_quoted(f, s),
+        }
+    }
+}
+
+fn write_current(f: &mut fmt::Formatter, path: &Path) -> fmt::Result {
+    f.write_str("@")?;
+    for node in path.iter() {
+        match *node {
+            PathNode::Identifier(ref key) => {
+                f.write_str(".")?;
+                write_key(f, key)?;
+            }
+            _ => write!(f, "{}", node)?,
+        }
+    }
+    Ok(())
+}
@@ -1,16 +1,31 @@
 use parser::PathParser;
+use std::cmp::Ordering;
 use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FromIterator;
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
 use crate::error::*;
 
+pub use self::diagnostic::PathParseError;
+pub use self::filter::{CompareOp, FilterExpr, Literal, Operand};
+
+mod diagnostic;
+mod filter;
+mod pointer;
+
 mod parser {
+    use pest::iterators::Pair;
     use pest::Parser;
 
+    use num_bigint::BigInt;
+
     use crate::error::*;
 
-    use super::{Path, PathNode};
+    use super::{
+        CompareOp, FilterExpr, Literal, Operand, Path, PathNode, PathParseError, PathSyntax,
+    };
 
     #[derive(Parser)]
     #[grammar = "path/path.pest"]
@@ -18,54 +33,503 @@ mod parser {
 
     impl PathParser {
         pub fn parse_to_path(s: &str) -> Result<Path> {
+            Self::parse_rule(Rule::path, PathSyntax::Slash, s)
+        }
+
+        pub fn parse_dotted_to_path(s: &str) -> Result<Path> {
+            Self::parse_rule(Rule::dotted_path, PathSyntax::Dotted, s)
+        }
+
+        fn parse_rule(rule: Rule, syntax: PathSyntax, s: &str) -> Result<Path> {
             let mut result: Vec<PathNode> = Vec::new();
-            let path = PathParser::parse(Rule::path, s)
-                .map_err(|e| Error::path_parse(e, s))?
+            let path = PathParser::parse(rule, s)
+                .map_err(|e| Error::path_parse(PathParseError::from_pest(e, s, syntax)))?
                 .next()
                 .unwrap();
             for sub_path in path.into_inner() {
-                if let Some(ident) = sub_path.into_inner().next() {
-                    match ident.as_rule() {
-                        Rule::path_ident => {
-                            result.push(PathNode::Identifier(ident.as_str().to_string()))
+                for node in sub_path.into_inner() {
+                    match node.as_rule() {
+                        Rule::path_ident | Rule::quoted_ident => {
+                            result.push(PathNode::Identifier(Self::parse_key(node, s)?))
                         }
-                        Rule::path_index_ident => {
-                            let mut path_index_ident_inner = ident.into_inner();
-                            result.push(PathNode::Identifier(
-                                path_index_ident_inner.next().unwrap().as_str().to_string(),
-                            ));
-                            result.push(PathNode::Index(
-                                path_index_ident_inner
-                                    .next()
-                                    .unwrap()
-                                    .as_str()
-                                    .parse()
-                                    .unwrap(),
-                            ));
+                        Rule::wildcard => result.push(PathNode::Wildcard),
+                        Rule::recursive => result.push(PathNode::Recursive),
+                        _ => result.push(Self::parse_selector(node, s)?),
+                    };
+                }
+            }
+
+            Ok(Path(result))
+        }
+
+        fn parse_selector(pair: Pair<Rule>, s: &str) -> Result<PathNode> {
+            match pair.as_rule() {
+                Rule::index => Ok(PathNode::Index(Self::parse_integer(pair, s)?)),
+                Rule::append => Ok(PathNode::Append),
+                Rule::insert_before => Ok(PathNode::InsertBefore(Self::parse_integer(
+                    pair.into_inner().next().unwrap(),
+                    s,
+                )?)),
+                Rule::slice => {
+                    let (mut start, mut end, mut step) = (None, None, None);
+                    for bound in pair.into_inner() {
+                        match bound.as_rule() {
+                            Rule::slice_start => start = Some(Self::parse_integer(bound, s)?),
+                            Rule::slice_end => end = Some(Self::parse_integer(bound, s)?),
+                            Rule::slice_step => {
+                                let value = Self::parse_integer(bound.clone(), s)?;
+                                if value == 0 {
+                                    return Err(Self::custom_error(
+                                        "slice step cannot be zero",
+                                        bound,
+                                        s,
+                                    ));
+                                }
+                                step = Some(value)
+                            }
+                            _ => unreachable!(),
                         }
+                    }
+                    Ok(PathNode::Slice {
+                        start,
+                        end,
+                        step: step.unwrap_or(1),
+                    })
+                }
+                Rule::filter => {
+                    Self::parse_filter(pair.into_inner().next().unwrap(), s).map(PathNode::Filter)
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        fn parse_filter(pair: Pair<Rule>, s: &str) -> Result<FilterExpr> {
+            match pair.as_rule() {
+                Rule::filter_or | Rule::filter_and => {
+                    let is_or = pair.as_rule() == Rule::filter_or;
+                    let mut inner = pair.into_inner();
+                    let mut expr = Self::parse_filter(inner.next().unwrap(), s)?;
+                    for right in inner {
+                        let right = Box::new(Self::parse_filter(right, s)?);
+                        expr = if is_or {
+                            FilterExpr::Or(Box::new(expr), right)
+                        } else {
+                            FilterExpr::And(Box::new(expr), right)
+                        };
+                    }
+                    Ok(expr)
+                }
+                Rule::filter_not => Ok(FilterExpr::Not(Box::new(Self::parse_filter(
+                    pair.into_inner().next().unwrap(),
+                    s,
+                )?))),
+                Rule::comparison => {
+                    let mut inner = pair.into_inner();
+                    let left = Self::parse_operand(inner.next().unwrap(), s)?;
+                    let op = match inner.next().unwrap().as_str() {
+                        "==" => CompareOp::Eq,
+                        "!=" => CompareOp::Ne,
+                        "<" => CompareOp::Lt,
+                        "<=" => CompareOp::Le,
+                        ">" => CompareOp::Gt,
+                        ">=" => CompareOp::Ge,
                         _ => unreachable!(),
                     };
+                    let right = Self::parse_operand(inner.next().unwrap(), s)?;
+                    Ok(FilterExpr::Compare(left, op, right))
                 }
+                Rule::filter_path => Ok(FilterExpr::Exists(Self::parse_filter_path(pair, s)?)),
+                _ => unreachable!(),
             }
+        }
+
+        fn parse_operand(pair: Pair<Rule>, s: &str) -> Result<Operand> {
+            let literal = match pair.as_rule() {
+                Rule::filter_path => {
+                    return Ok(Operand::Current(Self::parse_filter_path(pair, s)?))
+                }
+                Rule::number => {
+                    let text = pair.as_str();
+                    match text.parse::<BigInt>() {
+                        Ok(i) => Literal::Integer(i),
+                        Err(_) => match text.parse::<f64>() {
+                            Ok(f) => Literal::Float(f),
+                            Err(_) => return Err(Self::custom_error("invalid number", pair, s)),
+                        },
+                    }
+                }
+                Rule::string => Literal::String(Self::parse_key(pair, s)?),
+                Rule::boolean => Literal::Boolean(pair.as_str() == "true"),
+                Rule::nil => Literal::Nil,
+                _ => unreachable!(),
+            };
+            Ok(Operand::Literal(literal))
+        }
 
+        fn parse_filter_path(pair: Pair<Rule>, s: &str) -> Result<Path> {
+            let mut result = Vec::new();
+            for node in pair.into_inner() {
+                match node.as_rule() {
+                    Rule::index => result.push(PathNode::Index(Self::parse_integer(node, s)?)),
+                    _ => result.push(PathNode::Identifier(Self::parse_key(node, s)?)),
+                }
+            }
             Ok(Path(result))
         }
+
+        fn parse_integer(pair: Pair<Rule>, s: &str) -> Result<isize> {
+            pair.as_str()
+                .parse()
+                .map_err(|_| Self::custom_error("index is out of range", pair, s))
+        }
+
+        fn custom_error(message: &str, pair: Pair<Rule>, s: &str) -> Error {
+            let span = pair.as_span();
+            Error::path_parse(PathParseError::new(s, span.start()..span.end(), message))
+        }
+
+        fn parse_key(pair: Pair<Rule>, s: &str) -> Result<String> {
+            match pair.as_rule() {
+                Rule::path_ident => Ok(pair.as_str().to_string()),
+                Rule::quoted_ident | Rule::string => {
+                    let inner = pair.into_inner().next().unwrap();
+                    match Self::unescape(inner.as_str()) {
+                        Some(key) => Ok(key),
+                        None => Err(Self::custom_error(
+                            "invalid unicode escape in quoted key",
+                            inner,
+                            s,
+                        )),
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        /// Resolves the escapes of an already validated quoted key.
+        fn unescape(s: &str) -> Option<String> {
+            let mut result = String::with_capacity(s.len());
+            let mut chars = s.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    result.push(c);
+                    continue;
+                }
+                match chars.next()? {
+                    'n' => result.push('\n'),
+                    'r' => result.push('\r'),
+                    't' => result.push('\t'),
+                    'u' => {
+                        let hex: String =
+                            chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                        let code = u32::from_str_radix(&hex, 16).ok()?;
+                        result.push(std::char::from_u32(code)?);
+                    }
+                    c => result.push(c),
+                }
+            }
+            Some(result)
+        }
+    }
+}
+
+/// Writes `key` as a path segment, quoting it when it contains characters
+/// outside of `[A-Za-z0-9_-]`.
+fn write_key(f: &mut fmt::Formatter, key: &str) -> fmt::Result {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if plain {
+        f.write_str(key)
+    } else {
+        write_quoted(f, key)
+    }
+}
+
+/// Writes `s` in double quotes, escaping it as the path grammar expects.
+fn write_quoted(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
     }
+    f.write_str("\"")
 }
 
+/// A single step of a [`Path`].
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[non_exhaustive]
 pub enum PathNode {
+    /// Access a map entry by key.
     Identifier(String),
+    /// Access an array element, negative indices count from the end.
     Index(isize),
+    /// Match a Python-style range of array elements, written `[start:end:step]`.
+    ///
+    /// Missing bounds default to the whole array in the direction of `step`,
+    /// negative bounds count from the end. A zero `step` matches nothing.
+    Slice {
+        start: Option<isize>,
+        end: Option<isize>,
+        step: isize,
+    },
+    /// Match every array element, or map value, for which the expression
+    /// holds, written `[?(@.enabled == true)]`.
+    Filter(FilterExpr),
+    /// Match every direct child of a map or an array, written `*`.
+    Wildcard,
+    /// Match the current value and all of its descendants, written `**`.
+    Recursive,
+    /// The slot past the end of an array, written `[-]`.
+    ///
+    /// It never matches an existing value; setting it appends to the array.
+    Append,
+    /// A new slot before the element at an index, written `[^index]`.
+    ///
+    /// It never matches an existing value; setting it inserts into the array,
+    /// shifting that element and the following ones back.
+    InsertBefore(isize),
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+impl PathNode {
+    /// Returns `true` if this node matches at most one child.
+    pub fn is_singular(&self) -> bool {
+        matches!(
+            *self,
+            PathNode::Identifier(_)
+                | PathNode::Index(_)
+                | PathNode::Append
+                | PathNode::InsertBefore(_)
+        )
+    }
+
+    /// Returns `true` if this node is written in brackets after its parent.
+    fn is_selector(&self) -> bool {
+        matches!(
+            *self,
+            PathNode::Index(_)
+                | PathNode::Slice { .. }
+                | PathNode::Filter(_)
+                | PathNode::Append
+                | PathNode::InsertBefore(_)
+        )
+    }
+}
+
+impl fmt::Display for PathNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathNode::Identifier(ref key) => write_key(f, key),
+            PathNode::Index(index) => write!(f, "[{}]", index),
+            PathNode::Slice { start, end, step } => {
+                f.write_str("[")?;
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                f.write_str(":")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                if step != 1 {
+                    write!(f, ":{}", step)?;
+                }
+                f.write_str("]")
+            }
+            PathNode::Filter(ref expr) => write!(f, "[?({})]", expr),
+            PathNode::Wildcard => write!(f, "*"),
+            PathNode::Recursive => write!(f, "**"),
+            PathNode::Append => write!(f, "[-]"),
+            PathNode::InsertBefore(index) => write!(f, "[^{}]", index),
+        }
+    }
+}
+
+/// A parsed location inside a [`Value`](crate::Value), e.g. `/a/b[0]`.
+///
+/// An empty path refers to the root value.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Default)]
 pub struct Path(Vec<PathNode>);
 
-impl Default for Path {
-    fn default() -> Self {
+impl Path {
+    /// Returns the root path `/`.
+    pub fn root() -> Self {
         Path(Vec::new())
     }
+
+    /// Returns `true` if this path refers to the root value.
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Appends a map key to the path.
+    pub fn push_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.0.push(PathNode::Identifier(key.into()));
+        self
+    }
+
+    /// Appends an array slice to the path, see [`PathNode::Slice`].
+    pub fn push_slice(
+        &mut self,
+        start: Option<isize>,
+        end: Option<isize>,
+        step: isize,
+    ) -> &mut Self {
+        self.0.push(PathNode::Slice { start, end, step });
+        self
+    }
+
+    /// Appends an array index to the path.
+    pub fn push_index(&mut self, index: isize) -> &mut Self {
+        self.0.push(PathNode::Index(index));
+        self
+    }
+
+    /// Appends the append marker `[-]` to the path, see [`PathNode::Append`].
+    pub fn push_append(&mut self) -> &mut Self {
+        self.0.push(PathNode::Append);
+        self
+    }
+
+    /// Returns `true` if this path addresses at most one location, i.e. it
+    /// contains no wildcards.
+    pub fn is_singular(&self) -> bool {
+        self.0.iter().all(PathNode::is_singular)
+    }
+
+    /// Returns the path without its last node, or `None` for the root.
+    pub fn parent(&self) -> Option<Path> {
+        self.0.split_last().map(|(_, parent)| Path(parent.to_vec()))
+    }
+
+    /// Returns a new path with `other` appended to this one.
+    pub fn join(&self, other: &Path) -> Path {
+        let mut nodes = self.0.clone();
+        nodes.extend_from_slice(&other.0);
+        Path(nodes)
+    }
+
+    /// Returns `true` if `base` is a prefix of this path.
+    pub fn starts_with(&self, base: &Path) -> bool {
+        self.0.starts_with(&base.0)
+    }
+
+    /// Parses `s` in the given syntax.
+    pub fn parse_with(s: &str, syntax: PathSyntax) -> Result<Path> {
+        match syntax {
+            PathSyntax::Slash => PathParser::parse_to_path(s),
+            PathSyntax::Dotted => PathParser::parse_dotted_to_path(s),
+        }
+    }
+
+    /// Returns a value that displays this path in the dotted syntax, e.g.
+    /// `services.db.hosts[0]`. The root displays as the empty string.
+    pub fn dotted(&self) -> DottedPath<'_> {
+        DottedPath(self)
+    }
+
+    /// Returns the rest of this path after `base`, or `None` if `base` is not a prefix.
+    pub fn strip_prefix(&self, base: &Path) -> Option<Path> {
+        if self.starts_with(base) {
+            Some(Path(self.0[base.0.len()..].to_vec()))
+        } else {
+            None
+        }
+    }
+
+    /// Orders concrete paths node by node: keys before indices, keys by
+    /// their text and indices numerically. A path comes before the paths
+    /// below it.
+    pub(crate) fn cmp_concrete(&self, other: &Path) -> Ordering {
+        for (a, b) in self.0.iter().zip(other.0.iter()) {
+            let ordering = match (a, b) {
+                (PathNode::Identifier(a), PathNode::Identifier(b)) => a.cmp(b),
+                (PathNode::Index(a), PathNode::Index(b)) => a.cmp(b),
+                (PathNode::Identifier(_), _) => Ordering::Less,
+                _ => Ordering::Greater,
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        self.0.len().cmp(&other.0.len())
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "/");
+        }
+        for (i, node) in self.0.iter().enumerate() {
+            if i > 0 && node.is_selector() {
+                write!(f, "{}", node)?;
+            } else {
+                write!(f, "/{}", node)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The textual syntaxes a [`Path`] can be parsed from and displayed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathSyntax {
+    /// `/services/db/hosts[0]`, as used by [`Path::from_str`] and `Display`.
+    #[default]
+    Slash,
+    /// `services.db.hosts[0]`, as used by [`Dotted`] and [`Path::dotted`].
+    Dotted,
+}
+
+/// A path string in the dotted syntax, e.g. `services.db.hosts[0]`.
+///
+/// Every API taking a path accepts it in place of a slash-separated string,
+/// e.g. `value.get::<i32, _, _>(Dotted("services.db.port"))`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dotted<'a>(pub &'a str);
+
+impl<'a> TryFrom<Dotted<'a>> for Path {
+    type Error = Error;
+
+    fn try_from(value: Dotted<'a>) -> Result<Self> {
+        PathParser::parse_dotted_to_path(value.0)
+    }
+}
+
+/// Displays a [`Path`] in the dotted syntax, see [`Path::dotted`].
+#[derive(Debug, Clone, Copy)]
+pub struct DottedPath<'a>(&'a Path);
+
+impl<'a> fmt::Display for DottedPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, node) in self.0.iter().enumerate() {
+            if i > 0 && !node.is_selector() {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", node)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<PathNode>> for Path {
+    fn from(nodes: Vec<PathNode>) -> Self {
+        Path(nodes)
+    }
+}
+
+impl FromIterator<PathNode> for Path {
+    fn from_iter<I: IntoIterator<Item = PathNode>>(iter: I) -> Self {
+        Path(iter.into_iter().collect())
+    }
 }
 
 impl Deref for Path {
@@ -106,9 +570,19 @@ impl<'a> TryFrom<&'a str> for Path {
     }
 }
 
+impl<'a> TryFrom<&'a Path> for Path {
+    type Error = Error;
+
+    fn try_from(value: &'a Path) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
 #[allow(unused_imports)]
 mod tests {
-    use super::{Path, PathNode};
+    use std::convert::TryFrom;
+
+    use super::{CompareOp, Dotted, FilterExpr, Literal, Operand, Path, PathNode, PathSyntax};
 
     #[test]
     fn test_empty() {
@@ -163,4 +637,355 @@ mod tests {
         let parsed = "/a[0]/b/c[1]".parse::<Path>();
         assert!(matches!(parsed, Ok(path) if path == except_path ));
     }
+
+    #[test]
+    fn test_build() {
+        let mut path = Path::root();
+        assert!(path.is_root());
+        path.push_key("a").push_index(0).push_key("b");
+        let parsed = "/a[0]/b".parse::<Path>();
+        assert!(matches!(parsed, Ok(p) if p == path));
+    }
+
+    #[test]
+    fn test_parent_and_join() {
+        let path = "/a/b[1]".parse::<Path>().unwrap();
+        let parent = path.parent().unwrap();
+        assert_eq!(parent, "/a/b".parse::<Path>().unwrap());
+        assert_eq!(Path::root().parent(), None);
+
+        let tail = path.strip_prefix(&"/a".parse().unwrap()).unwrap();
+        assert_eq!(
+            tail,
+            Path::from(vec![
+                PathNode::Identifier("b".to_string()),
+                PathNode::Index(1)
+            ])
+        );
+        assert_eq!("/a".parse::<Path>().unwrap().join(&tail), path);
+        assert!(path.starts_with(&parent));
+        assert!(!parent.starts_with(&path));
+        assert_eq!(path.strip_prefix(&"/b".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for s in &[
+            "/",
+            "/a",
+            "/a[0]/b/c[1]",
+            "/a-b/c_d[-1]",
+            "/a[1:-1]",
+            "/a[::2]/b",
+            "/a[:]",
+            "/a[-2::-1]",
+        ] {
+            let path = s.parse::<Path>().unwrap();
+            assert_eq!(path.to_string(), *s);
+        }
+    }
+
+    #[test]
+    fn test_quoted() {
+        let except_path = Path(vec![
+            PathNode::Identifier("labels".to_string()),
+            PathNode::Identifier("app.kubernetes.io/name".to_string()),
+            PathNode::Identifier("a \"b\"\\".to_string()),
+            PathNode::Index(2),
+            PathNode::Identifier("名字\n".to_string()),
+        ]);
+        let parsed =
+            r#"/labels/"app.kubernetes.io/name"/"a \"b\"\\"[2]/"\u{540d}字\n""#.parse::<Path>();
+        assert!(matches!(parsed, Ok(path) if path == except_path ));
+    }
+
+    #[test]
+    fn test_quoted_invalid() {
+        assert!(r#"/"unterminated"#.parse::<Path>().is_err());
+        assert!(r#"/"bad \q escape""#.parse::<Path>().is_err());
+        assert!(r#"/"\u{d800}""#.parse::<Path>().is_err());
+        assert!("/a b".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_quoted_display_round_trip() {
+        let mut path = Path::root();
+        path.push_key("app.kubernetes.io/name")
+            .push_key("")
+            .push_key("with \"quote\" and \\ and \u{1}")
+            .push_index(0)
+            .push_key("plain");
+        let display = path.to_string();
+        assert_eq!(
+            display,
+            r#"/"app.kubernetes.io/name"/""/"with \"quote\" and \\ and \u{1}"[0]/plain"#
+        );
+        assert!(matches!(display.parse::<Path>(), Ok(p) if p == path));
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let except_path = Path(vec![
+            PathNode::Identifier("services".to_string()),
+            PathNode::Wildcard,
+            PathNode::Identifier("port".to_string()),
+            PathNode::Recursive,
+            PathNode::Identifier("name".to_string()),
+        ]);
+        let parsed = "/services/*/port/**/name".parse::<Path>();
+        assert!(matches!(parsed, Ok(ref path) if *path == except_path && !path.is_singular()));
+        assert_eq!(except_path.to_string(), "/services/*/port/**/name");
+        assert!("/a[0]/b".parse::<Path>().unwrap().is_singular());
+        assert!("/***".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_slices() {
+        let slice = |start, end, step| PathNode::Slice { start, end, step };
+        let cases = vec![
+            ("/a[-1]", PathNode::Index(-1)),
+            ("/a[1:-1]", slice(Some(1), Some(-1), 1)),
+            ("/a[::2]", slice(None, None, 2)),
+            ("/a[:3:]", slice(None, Some(3), 1)),
+            ("/a[5::-1]", slice(Some(5), None, -1)),
+        ];
+        for (s, node) in cases {
+            let except_path = Path(vec![PathNode::Identifier("a".to_string()), node]);
+            assert!(
+                matches!(s.parse::<Path>(), Ok(ref path) if *path == except_path && (s == "/a[-1]") == path.is_singular())
+            );
+        }
+
+        assert!("/a[::0]".parse::<Path>().is_err());
+        assert!("/a[1:2:3:4]".parse::<Path>().is_err());
+        assert!("/a[99999999999999999999]".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_chained_indices() {
+        let grid = |indices: &[isize]| {
+            let mut path = Path::root();
+            path.push_key("grid");
+            for &index in indices {
+                path.push_index(index);
+            }
+            path
+        };
+        assert!(matches!("/grid[1][2]".parse::<Path>(), Ok(ref path) if *path == grid(&[1, 2])));
+        assert!(
+            matches!("/grid[0][-1][3]".parse::<Path>(), Ok(ref path) if *path == grid(&[0, -1, 3]))
+        );
+
+        let mut root_index = Path::root();
+        root_index.push_index(0).push_key("name");
+        assert!(matches!("/[0]/name".parse::<Path>(), Ok(ref path) if *path == root_index));
+        assert!(matches!("/[0][1]".parse::<Path>(), Ok(ref path) if path.len() == 2));
+        assert!(matches!("/*[0]".parse::<Path>(), Ok(ref path) if path[0] == PathNode::Wildcard));
+
+        for s in &[
+            "/grid[1][2]",
+            "/[0]/name",
+            "/[0][1]",
+            "/*[0]",
+            "/a[1:][?(@.b)]",
+        ] {
+            let path = s.parse::<Path>().unwrap();
+            assert_eq!(path.to_string(), *s);
+        }
+
+        assert!("/grid[1]x".parse::<Path>().is_err());
+        assert!("/[]".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        let mut except_path = Path::root();
+        except_path
+            .push_key("a")
+            .push_index(0)
+            .push_key("b/c")
+            .push_key("m~n")
+            .push_key("01")
+            .push_key("");
+        let pointer = "/a/0/b~1c/m~0n/01/";
+        assert!(matches!(Path::from_json_pointer(pointer), Ok(ref path) if *path == except_path));
+        assert_eq!(except_path.to_json_pointer().as_deref(), Some(pointer));
+
+        assert!(matches!(Path::from_json_pointer(""), Ok(ref path) if path.is_root()));
+        assert_eq!(Path::root().to_json_pointer().as_deref(), Some(""));
+
+        assert!(Path::from_json_pointer("a/b").is_err());
+        assert!(Path::from_json_pointer("/a~2").is_err());
+        assert!(Path::from_json_pointer("/a~").is_err());
+
+        assert!("/a[-1]"
+            .parse::<Path>()
+            .unwrap()
+            .to_json_pointer()
+            .is_none());
+        assert!("/a/*".parse::<Path>().unwrap().to_json_pointer().is_none());
+    }
+
+    #[test]
+    fn test_dotted() {
+        let mut except_path = Path::root();
+        except_path
+            .push_key("services")
+            .push_key("db")
+            .push_key("hosts")
+            .push_index(0);
+        let parsed = Path::try_from(Dotted("services.db.hosts[0]"));
+        assert!(matches!(parsed, Ok(ref path) if *path == except_path));
+        assert!(
+            matches!(Path::parse_with("/services/db/hosts[0]", PathSyntax::Slash), Ok(ref path) if *path == except_path)
+        );
+        assert_eq!(except_path.dotted().to_string(), "services.db.hosts[0]");
+
+        assert!(matches!(Path::parse_with("", PathSyntax::Dotted), Ok(ref path) if path.is_root()));
+        assert_eq!(Path::root().dotted().to_string(), "");
+
+        for s in &[
+            "a",
+            "[0].name",
+            "grid[1][2]",
+            r#"labels."app.kubernetes.io/name""#,
+            "services.*.port",
+            "**.name",
+            "users[?(@.age > 30)].name",
+        ] {
+            let path = Path::parse_with(s, PathSyntax::Dotted).unwrap();
+            assert_eq!(path.dotted().to_string(), *s);
+            let slash = path.to_string().parse::<Path>().unwrap();
+            assert_eq!(slash, path);
+        }
+
+        assert!(Path::parse_with("a..b", PathSyntax::Dotted).is_err());
+        assert!(Path::parse_with(".a", PathSyntax::Dotted).is_err());
+        assert!(Path::parse_with("a.", PathSyntax::Dotted).is_err());
+        assert!(Path::parse_with("/a", PathSyntax::Dotted).is_err());
+    }
+
+    #[test]
+    fn test_insert_markers() {
+        let mut except_path = Path::root();
+        except_path.push_key("list").push_append();
+        assert!(
+            matches!("/list[-]".parse::<Path>(), Ok(ref path) if *path == except_path && path.is_singular())
+        );
+
+        let parsed = "/list[^2]/name".parse::<Path>();
+        assert!(matches!(parsed, Ok(ref path) if path[1] == PathNode::InsertBefore(2)));
+        let parsed = "/list[^-1]".parse::<Path>();
+        assert!(matches!(parsed, Ok(ref path) if path[1] == PathNode::InsertBefore(-1)));
+
+        for s in &["/list[-]", "/list[^2]/name", "/[-][^0]"] {
+            assert_eq!(s.parse::<Path>().unwrap().to_string(), *s);
+        }
+        assert_eq!(except_path.to_json_pointer().as_deref(), Some("/list/-"));
+        assert!(matches!(Path::from_json_pointer("/list/-"), Ok(ref path) if *path == except_path));
+
+        assert!("/list[+]".parse::<Path>().is_err());
+        assert!("/list[^]".parse::<Path>().is_err());
+        assert!("/list[--]".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_filters() {
+        let current =
+            |key: &str| Operand::Current(Path(vec![PathNode::Identifier(key.to_string())]));
+        let parsed = r#"/listeners[?(@.name == "http")]/port"#.parse::<Path>();
+        let except_path = Path(vec![
+            PathNode::Identifier("listeners".to_string()),
+            PathNode::Filter(FilterExpr::Compare(
+                current("name"),
+                CompareOp::Eq,
+                Operand::Literal(Literal::String("http".to_string())),
+            )),
+            PathNode::Identifier("port".to_string()),
+        ]);
+        assert!(matches!(parsed, Ok(ref path) if *path == except_path && !path.is_singular()));
+
+        let parsed = "/users[?(@.age > 30 && (@.admin || !@.tags[0]))]".parse::<Path>();
+        let except_path = Path(vec![
+            PathNode::Identifier("users".to_string()),
+            PathNode::Filter(FilterExpr::And(
+                Box::new(FilterExpr::Compare(
+                    current("age"),
+                    CompareOp::Gt,
+                    Operand::Literal(Literal::Integer(30.into())),
+                )),
+                Box::new(FilterExpr::Or(
+                    Box::new(FilterExpr::Exists(Path(vec![PathNode::Identifier(
+                        "admin".to_string(),
+                    )]))),
+                    Box::new(FilterExpr::Not(Box::new(FilterExpr::Exists(Path(vec![
+                        PathNode::Identifier("tags".to_string()),
+                        PathNode::Index(0),
+                    ]))))),
+                )),
+            )),
+        ]);
+        assert!(matches!(parsed, Ok(ref path) if *path == except_path));
+
+        assert!("/a[?(@.x ==)]".parse::<Path>().is_err());
+        assert!("/a[?()]".parse::<Path>().is_err());
+        assert!("/a[?(@.x = 1)]".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_filter_display_round_trip() {
+        for s in &[
+            r#"/a[?(@.name == "http")]/port"#,
+            "/a[?(@ >= -1.5 || @ < 2)]",
+            "/a[?((@.x || @.y) && !(@.z != nil))]",
+            r#"/a[?(@."b.c"[1] == true)]"#,
+        ] {
+            let path = s.parse::<Path>().unwrap();
+            assert_eq!(path.to_string(), *s);
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = "/db(0)".parse::<Path>().unwrap_err();
+        let details = err.path_parse_error().unwrap();
+        assert_eq!(details.path(), "/db(0)");
+        assert_eq!(details.offset(), 3);
+        assert_eq!(details.expected(), ["end of path"]);
+        assert_eq!(details.hint(), Some("did you mean `/db[0]`?"));
+        assert_eq!(
+            err.to_string(),
+            "invalid path `/db(0)`\n  |\n  | /db(0)\n  |    ^ unexpected `(`, expected end of path\n  |\n  = help: did you mean `/db[0]`?"
+        );
+
+        let err = "db.port".parse::<Path>().unwrap_err();
+        assert_eq!(
+            err.path_parse_error().unwrap().hint(),
+            Some("did you mean `/db/port`?")
+        );
+        let err = "/a b".parse::<Path>().unwrap_err();
+        assert_eq!(
+            err.path_parse_error().unwrap().hint(),
+            Some("did you mean `/\"a b\"`?")
+        );
+        let err = Path::parse_with("/a/b", PathSyntax::Dotted).unwrap_err();
+        assert_eq!(
+            err.path_parse_error().unwrap().hint(),
+            Some("did you mean `a.b`?")
+        );
+
+        let err = "/a[x]".parse::<Path>().unwrap_err();
+        let details = err.path_parse_error().unwrap();
+        assert_eq!(details.offset(), 3);
+        assert!(details.expected().iter().any(|token| token == "an index"));
+        assert_eq!(details.hint(), None);
+
+        let err = "/a[99999999999999999999999]".parse::<Path>().unwrap_err();
+        let details = err.path_parse_error().unwrap();
+        assert_eq!(details.span(), 3..26);
+        assert_eq!(details.message(), "index is out of range");
+        assert!(err
+            .to_string()
+            .contains(&format!("  |    {} index", "^".repeat(23))));
+    }
 }
@@ -0,0 +1,102 @@
+use std::fmt::Write;
+
+use crate::error::*;
+
+use super::{Path, PathNode, PathParseError};
+
+impl Path {
+    /// Parses an RFC 6901 JSON Pointer such as `/a/0/b~1c`.
+    ///
+    /// The empty string is the root. Segments that are array indices in the
+    /// pointer syntax (`0`, `1`, ... without leading zeros) become
+    /// [`PathNode::Index`], `-` becomes [`PathNode::Append`], and all others
+    /// become [`PathNode::Identifier`], with `~1` and `~0` unescaped to `/`
+    /// and `~`.
+    pub fn from_json_pointer(pointer: &str) -> Result<Path> {
+        if pointer.is_empty() {
+            return Ok(Path::root());
+        }
+        if !pointer.starts_with('/') {
+            return Err(pointer_error(
+                "a JSON pointer must start with `/`",
+                pointer,
+                0,
+            ));
+        }
+
+        let mut path = Path::root();
+        let mut offset = 1;
+        for segment in pointer[1..].split('/') {
+            path.push(parse_segment(segment, pointer, offset)?);
+            offset += segment.len() + 1;
+        }
+        Ok(path)
+    }
+
+    /// Renders this path as an RFC 6901 JSON Pointer, e.g. `/a/0/b~1c`.
+    ///
+    /// Returns `None` if the path is not singular or contains a negative
+    /// index or an insert marker, since none of them can be expressed as a
+    /// pointer.
+    pub fn to_json_pointer(&self) -> Option<String> {
+        let mut pointer = String::new();
+        for node in self.iter() {
+            match *node {
+                PathNode::Identifier(ref key) => {
+                    pointer.push('/');
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                }
+                PathNode::Index(index) if index >= 0 => {
+                    write!(pointer, "/{}", index).unwrap();
+                }
+                PathNode::Append => pointer.push_str("/-"),
+                _ => return None,
+            }
+        }
+        Some(pointer)
+    }
+}
+
+fn parse_segment(segment: &str, pointer: &str, offset: usize) -> Result<PathNode> {
+    if segment == "-" {
+        return Ok(PathNode::Append);
+    }
+    let is_index = segment == "0"
+        || (!segment.starts_with('0')
+            && !segment.is_empty()
+            && segment.bytes().all(|b| b.is_ascii_digit()));
+    if is_index {
+        if let Ok(index) = segment.parse() {
+            return Ok(PathNode::Index(index));
+        }
+    }
+
+    let mut key = String::with_capacity(segment.len());
+    let mut chars = segment.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '~' {
+            key.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, '0')) => key.push('~'),
+            Some((_, '1')) => key.push('/'),
+            _ => {
+                return Err(pointer_error(
+                    "`~` must be followed by `0` or `1`",
+                    pointer,
+                    offset + i,
+                ))
+            }
+        }
+    }
+    Ok(PathNode::Identifier(key))
+}
+
+fn pointer_error(message: &str, pointer: &str, offset: usize) -> Error {
+    let end = pointer[offset..]
+        .chars()
+        .next()
+        .map_or(offset, |c| offset + c.len_utf8());
+    Error::path_parse(PathParseError::new(pointer, offset..end, message))
+}
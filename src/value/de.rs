@@ -1,12 +1,168 @@
-use std::collections::{HashMap, VecDeque};
-use std::convert::TryInto;
+use std::collections::VecDeque;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::iter::Enumerate;
 
+use num_bigint::BigInt;
 use num_traits::ToPrimitive;
 use serde::de;
 
 use crate::error::{Error, Result};
-use crate::value::Value;
+use crate::path::{Path, PathNode};
+use crate::value::{Map, Value};
+
+impl<'de> de::Deserialize<'de> for Value {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        // `deserialize_any` rather than `deserialize_option`, so that an absent
+        // field is reported as missing instead of silently becoming `Nil`.
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// The key serde_json uses to pass a number as text when its
+/// `arbitrary_precision` feature is on, e.g. because another crate enabled it.
+const SERDE_JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Parses the text of a number, keeping integers of any size exact. Floats
+/// that overflow to infinity are an error.
+pub(crate) fn parse_number(s: &str) -> Result<Value> {
+    if let Ok(value) = s.parse::<BigInt>() {
+        return Ok(Value::Integer(value));
+    }
+    match s.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(Value::Float(value)),
+        Ok(_) => Err(Error::serde(format!("number {} is out of range", s))),
+        Err(_) => Err(Error::serde(format!("invalid number {}", s))),
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid value")
+    }
+
+    #[inline]
+    fn visit_bool<E>(self, value: bool) -> std::result::Result<Value, E> {
+        Ok(Value::Boolean(value))
+    }
+
+    #[inline]
+    fn visit_i64<E>(self, value: i64) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(BigInt::from(value)))
+    }
+
+    #[inline]
+    fn visit_i128<E>(self, value: i128) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(BigInt::from(value)))
+    }
+
+    #[inline]
+    fn visit_u64<E>(self, value: u64) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(BigInt::from(value)))
+    }
+
+    #[inline]
+    fn visit_u128<E>(self, value: u128) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(BigInt::from(value)))
+    }
+
+    #[inline]
+    fn visit_f64<E>(self, value: f64) -> std::result::Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    #[inline]
+    fn visit_str<E>(self, value: &str) -> std::result::Result<Value, E> {
+        Ok(Value::String(value.into()))
+    }
+
+    #[inline]
+    fn visit_string<E>(self, value: String) -> std::result::Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    #[inline]
+    fn visit_none<E>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    #[inline]
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    #[inline]
+    fn visit_unit<E>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    #[inline]
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            array.push(element);
+        }
+        Ok(Value::Array(array))
+    }
+
+    fn visit_enum<A>(self, data: A) -> std::result::Result<Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        // Tagged values such as YAML `!Tag value` become a map with one key.
+        use serde::de::VariantAccess;
+        let (tag, variant): (String, _) = data.variant()?;
+        let mut table = Map::with_capacity(1);
+        table.insert(tag, variant.newtype_variant()?);
+        Ok(Value::Map(table))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut table = Map::with_capacity(map.size_hint().unwrap_or(0));
+        // Formats like YAML allow scalar keys of any type, keep their text form.
+        while let Some(key) = map.next_key::<Value>()? {
+            let key = match key {
+                Value::Nil | Value::Array(_) | Value::Map(_) => {
+                    return Err(de::Error::custom(format!(
+                        "unsupported map key: found {}, but keys must be strings, numbers or booleans",
+                        key.unexpected()
+                    )));
+                }
+                key => String::try_from(key).map_err(de::Error::custom)?,
+            };
+            if table.is_empty() && key == SERDE_JSON_NUMBER_TOKEN {
+                let number: String = map.next_value()?;
+                return parse_number(&number).map_err(de::Error::custom);
+            }
+            table.insert(key, map.next_value()?);
+        }
+        Ok(Value::Map(table))
+    }
+}
 
 impl<'de> de::Deserializer<'de> for Value {
     type Error = Error;
@@ -19,10 +175,19 @@ impl<'de> de::Deserializer<'de> for Value {
         // Deserialize based on the underlying type
         match self {
             Value::Nil => visitor.visit_unit(),
-            Value::Integer(i) => match i.to_i64() {
-                Some(v) => visitor.visit_i64(v),
-                None => Err(Error::too_large(i)),
-            },
+            Value::Integer(i) => {
+                if let Some(v) = i.to_i64() {
+                    visitor.visit_i64(v)
+                } else if let Some(v) = i.to_u64() {
+                    visitor.visit_u64(v)
+                } else if let Some(v) = i.to_i128() {
+                    visitor.visit_i128(v)
+                } else if let Some(v) = i.to_u128() {
+                    visitor.visit_u128(v)
+                } else {
+                    Err(Error::too_large(i))
+                }
+            }
             Value::Boolean(b) => visitor.visit_bool(b),
             Value::Float(f) => visitor.visit_f64(f),
             Value::String(s) => visitor.visit_string(s),
@@ -76,6 +241,16 @@ impl<'de> de::Deserializer<'de> for Value {
         visitor.visit_u64(self.try_into()?)
     }
 
+    #[inline]
+    fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.try_into()?)
+    }
+
+    #[inline]
+    fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(self.try_into()?)
+    }
+
     #[inline]
     fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         visitor.visit_f32(self.try_into()?)
@@ -181,7 +356,10 @@ impl<'de> de::SeqAccess<'de> for SeqAccess {
         T: de::DeserializeSeed<'de>,
     {
         match self.elements.next() {
-            Some((idx, value)) => seed.deserialize(value).map(Some),
+            Some((idx, value)) => seed
+                .deserialize(value)
+                .map(Some)
+                .map_err(|err| err.within(&Path::from(vec![PathNode::Index(idx as isize)]))),
             None => Ok(None),
         }
     }
@@ -199,7 +377,7 @@ struct MapAccess {
 }
 
 impl MapAccess {
-    fn new(table: HashMap<String, Value>) -> Self {
+    fn new(table: Map) -> Self {
         MapAccess {
             elements: table.into_iter().collect(),
         }
@@ -213,7 +391,7 @@ impl<'de> de::MapAccess<'de> for MapAccess {
     where
         K: de::DeserializeSeed<'de>,
     {
-        if let Some(&(ref key_s, _)) = self.elements.front() {
+        if let Some((key_s, _)) = self.elements.front() {
             let key_de = Value::String(key_s.clone());
             let key = de::DeserializeSeed::deserialize(seed, key_de)?;
 
@@ -229,6 +407,7 @@ impl<'de> de::MapAccess<'de> for MapAccess {
     {
         let (key, value) = self.elements.pop_front().unwrap();
         de::DeserializeSeed::deserialize(seed, value)
+            .map_err(|err| err.within(&Path::from(vec![PathNode::Identifier(key)])))
     }
 }
 
@@ -239,15 +418,15 @@ struct EnumAccess {
 }
 
 impl EnumAccess {
-    fn variant_deserializer(&self, name: &str) -> Result<StrDeserializer> {
+    fn variant_deserializer(&self, name: &str) -> Result<StrDeserializer<'_>> {
         self.variants
             .iter()
             .find(|s| **s == name)
-            .map(|s| StrDeserializer(*s))
+            .map(|s| StrDeserializer(s))
             .ok_or_else(|| self.no_constructor_error(name))
     }
 
-    fn table_deserializer(&self, table: &HashMap<String, Value>) -> Result<StrDeserializer> {
+    fn table_deserializer(&self, table: &Map) -> Result<StrDeserializer<'_>> {
         if table.len() == 1 {
             self.variant_deserializer(table.iter().next().unwrap().0)
         } else {
@@ -281,7 +460,7 @@ impl<'de> de::EnumAccess<'de> for EnumAccess {
         let value = {
             let deserializer = match self.value {
                 Value::String(ref s) => self.variant_deserializer(s),
-                Value::Map(ref t) => self.table_deserializer(&t),
+                Value::Map(ref t) => self.table_deserializer(t),
                 _ => Err(self.structural_error()),
             }?;
             seed.deserialize(deserializer)?
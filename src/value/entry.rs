@@ -0,0 +1,237 @@
+use std::convert::TryInto;
+
+use crate::error::{Error, Result};
+use crate::path::{Path, PathNode};
+use crate::value::{Map, SetOptions, Value};
+
+/// A view into a single location of a [`Value`], which may either be
+/// occupied or vacant. Constructed by [`Value::entry`].
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+/// An existing location of a [`Value`].
+pub struct OccupiedEntry<'a> {
+    path: Path,
+    value: &'a mut Value,
+}
+
+/// A missing location of a [`Value`]. Inserting creates the maps and arrays
+/// leading up to it.
+pub struct VacantEntry<'a> {
+    path: Path,
+    root: &'a mut Value,
+}
+
+impl Value {
+    /// Gets the entry at `path` for in-place manipulation.
+    ///
+    /// Fails if `path` is not singular, because a vacant location could not be
+    /// created from it, or if inserting would pad an array with more elements
+    /// than [`Value::set`] allows.
+    pub fn entry<P, IntoErr>(&mut self, path: P) -> Result<Entry<'_>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        if !path.is_singular() {
+            return Err(Error::not_singular(path));
+        }
+
+        if self.select_first(&path).is_none() {
+            self.check_set_path(&path, &SetOptions::default())?;
+            return Ok(Entry::Vacant(VacantEntry { path, root: self }));
+        }
+        match self.walk_mut(&path) {
+            Some(value) => Ok(Entry::Occupied(OccupiedEntry { path, value })),
+            None => unreachable!(),
+        }
+    }
+
+    /// Follows a singular path without creating anything.
+    pub(crate) fn walk(&self, nodes: &[PathNode]) -> Option<&Value> {
+        let mut value = self;
+        for node in nodes {
+            value = match (node, value) {
+                (PathNode::Identifier(key), Value::Map(map)) => map.get(key)?,
+                (PathNode::Index(index), Value::Array(array)) => {
+                    array.get(Value::map_index(*index, array.len())?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Mutable counterpart of [`Value::walk`].
+    pub(crate) fn walk_mut(&mut self, nodes: &[PathNode]) -> Option<&mut Value> {
+        let mut value = self;
+        for node in nodes {
+            value = match (node, value) {
+                (PathNode::Identifier(key), Value::Map(map)) => map.get_mut(key)?,
+                (PathNode::Index(index), Value::Array(array)) => {
+                    let index = Value::map_index(*index, array.len())?;
+                    array.get_mut(index)?
+                }
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Returns the child at `node`, replacing `self` with an empty container
+    /// of the right kind and padding arrays with `Nil` as needed.
+    pub(crate) fn child_or_insert(&mut self, node: &PathNode) -> &mut Value {
+        match *node {
+            PathNode::Identifier(ref key) => {
+                if !matches!(*self, Value::Map(_)) {
+                    *self = Value::Map(Map::new());
+                }
+                match *self {
+                    Value::Map(ref mut map) => map.entry(key.clone()).or_default(),
+                    _ => unreachable!(),
+                }
+            }
+            PathNode::Index(_) | PathNode::Append | PathNode::InsertBefore(_) => {
+                if !matches!(*self, Value::Array(_)) {
+                    *self = Value::Array(Vec::new());
+                }
+                let array = match *self {
+                    Value::Array(ref mut array) => array,
+                    _ => unreachable!(),
+                };
+                match *node {
+                    PathNode::Append => {
+                        array.push(Value::Nil);
+                        array.last_mut().unwrap()
+                    }
+                    PathNode::InsertBefore(index) => match Value::map_index(index, array.len()) {
+                        Some(at) if at <= array.len() => {
+                            array.insert(at, Value::Nil);
+                            &mut array[at]
+                        }
+                        _ => array_slot(array, index),
+                    },
+                    PathNode::Index(index) => array_slot(array, index),
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!("vacant entries only hold singular paths"),
+        }
+    }
+}
+
+/// Returns the slot at `index`, growing the array with `Nil` at the end (or
+/// at the front, for negative indices) until it exists.
+fn array_slot(array: &mut Vec<Value>, index: isize) -> &mut Value {
+    let len = array.len();
+    let index = match Value::map_index(index, len) {
+        Some(index) => {
+            if index >= len {
+                array.resize(index + 1, Value::Nil);
+            }
+            index
+        }
+        None => {
+            let missing = index.unsigned_abs() - len;
+            array.splice(0..0, vec![Value::Nil; missing]);
+            0
+        }
+    };
+    &mut array[index]
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the path of this entry.
+    pub fn path(&self) -> &Path {
+        match *self {
+            Entry::Occupied(ref entry) => entry.path(),
+            Entry::Vacant(ref entry) => entry.path(),
+        }
+    }
+
+    /// Inserts `default` if the entry is vacant, and returns the value.
+    pub fn or_insert<V: Into<Value>>(self, default: V) -> &'a mut Value {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `default` if the entry is vacant, and returns
+    /// the value.
+    pub fn or_insert_with<V, F>(self, default: F) -> &'a mut Value
+    where
+        V: Into<Value>,
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Inserts `Nil` if the entry is vacant, and returns the value.
+    pub fn or_default(self) -> &'a mut Value {
+        self.or_insert(Value::Nil)
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut Value),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the path of this entry.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self) -> &Value {
+        self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.value
+    }
+
+    /// Converts the entry into a reference bound to the original value.
+    pub fn into_mut(self) -> &'a mut Value {
+        self.value
+    }
+
+    /// Replaces the value and returns the previous one.
+    pub fn insert<V: Into<Value>>(&mut self, value: V) -> Value {
+        std::mem::replace(self.value, value.into())
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the path of this entry.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Inserts `value`, creating missing maps and arrays on the way. Values
+    /// along the path that are not containers of the right kind are replaced.
+    pub fn insert<V: Into<Value>>(self, value: V) -> &'a mut Value {
+        let mut target = self.root;
+        for node in self.path.iter() {
+            target = target.child_or_insert(node);
+        }
+        *target = value.into();
+        target
+    }
+}
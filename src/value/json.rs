@@ -0,0 +1,230 @@
+use std::fmt;
+use std::io;
+
+use num_traits::ToPrimitive;
+use serde::de;
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq};
+use serde_json::value::RawValue;
+
+use crate::error::{Error, Result};
+use crate::value::de::parse_number;
+use crate::value::{Map, Value};
+
+const ORIGIN: &str = "JSON";
+
+impl Value {
+    /// Parses a JSON document.
+    ///
+    /// Integers of any size become [`Value::Integer`] without losing
+    /// precision; numbers too large for a float are an error. Errors are
+    /// reported with their line and column.
+    pub fn from_json_str(s: &str) -> Result<Value> {
+        // Checking the whole document first reports syntax errors with
+        // serde_json's positions; the reader then only sees valid JSON.
+        let _: de::IgnoredAny = serde_json::from_str(s).map_err(json_error)?;
+        Reader {
+            document: s,
+            pos: 0,
+        }
+        .value(0)
+    }
+
+    /// Parses a JSON document from `reader`, see [`Value::from_json_str`].
+    pub fn from_json_reader<R: io::Read>(mut reader: R) -> Result<Value> {
+        let mut s = String::new();
+        reader.read_to_string(&mut s).map_err(Error::io)?;
+        Value::from_json_str(&s)
+    }
+
+    /// Renders this value as compact JSON.
+    ///
+    /// Fails if the value contains a float that is NaN or infinite.
+    pub fn to_json_string(&self) -> Result<String> {
+        serde_json::to_string(&Json(self)).map_err(|err| Error::serde(err.to_string()))
+    }
+
+    /// Renders this value as indented JSON, see [`Value::to_json_string`].
+    pub fn to_json_pretty(&self) -> Result<String> {
+        serde_json::to_string_pretty(&Json(self)).map_err(|err| Error::serde(err.to_string()))
+    }
+}
+
+fn json_error(err: serde_json::Error) -> Error {
+    if err.is_io() {
+        Error::io(err.into())
+    } else {
+        Error::format_parse(ORIGIN, err)
+    }
+}
+
+/// How deeply arrays and objects may nest, the same limit as serde_json's.
+const MAX_DEPTH: usize = 128;
+
+/// Converts a valid JSON document in one pass.
+///
+/// serde_json reads integers past 64 bits as floats, so numbers are taken
+/// from their source text instead.
+struct Reader<'a> {
+    document: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the value at the current position, nested in `depth` arrays or
+    /// objects.
+    fn value(&mut self, depth: usize) -> Result<Value> {
+        self.skip_whitespace();
+        let start = self.pos;
+        Ok(match self.next_byte() {
+            b'{' => {
+                self.check_depth(start, depth)?;
+                let mut map = Map::new();
+                self.skip_whitespace();
+                if self.peek_byte() == b'}' {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.skip_whitespace();
+                        self.pos += 1; // `:`
+                        map.insert(key, self.value(depth + 1)?);
+                        self.skip_whitespace();
+                        if self.next_byte() == b'}' {
+                            break;
+                        }
+                    }
+                }
+                Value::Map(map)
+            }
+            b'[' => {
+                self.check_depth(start, depth)?;
+                let mut array = Vec::new();
+                self.skip_whitespace();
+                if self.peek_byte() == b']' {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        array.push(self.value(depth + 1)?);
+                        self.skip_whitespace();
+                        if self.next_byte() == b']' {
+                            break;
+                        }
+                    }
+                }
+                Value::Array(array)
+            }
+            b'"' => {
+                self.pos = start;
+                Value::String(self.string()?)
+            }
+            b't' => {
+                self.pos += 3;
+                Value::Boolean(true)
+            }
+            b'f' => {
+                self.pos += 4;
+                Value::Boolean(false)
+            }
+            b'n' => {
+                self.pos += 3;
+                Value::Nil
+            }
+            _ => {
+                let rest = &self.document.as_bytes()[start..];
+                let len = rest
+                    .iter()
+                    .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                    .unwrap_or(rest.len());
+                self.pos = start + len;
+                parse_number(&self.document[start..self.pos])
+                    .map_err(|err| self.error_at(start, err))?
+            }
+        })
+    }
+
+    /// Reads the string starting at the current position.
+    fn string(&mut self) -> Result<String> {
+        let start = self.pos;
+        let bytes = self.document.as_bytes();
+        let mut end = start + 1;
+        while bytes[end] != b'"' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        self.pos = end + 1;
+        serde_json::from_str(&self.document[start..self.pos]).map_err(json_error)
+    }
+
+    fn check_depth(&self, start: usize, depth: usize) -> Result<()> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error_at(start, "recursion limit exceeded"));
+        }
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        let bytes = self.document.as_bytes();
+        while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek_byte(&self) -> u8 {
+        self.document.as_bytes()[self.pos]
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.peek_byte();
+        self.pos += 1;
+        byte
+    }
+
+    /// Reports `message` at the byte `offset` of the document.
+    fn error_at<M: fmt::Display>(&self, offset: usize, message: M) -> Error {
+        let before = &self.document[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Error::format_parse(
+            ORIGIN,
+            Error::serde(format!("{} at line {} column {}", message, line, column)),
+        )
+    }
+}
+
+/// Serializes a [`Value`] as JSON, writing integers past 128 bits as their
+/// digits and refusing floats that JSON cannot represent.
+struct Json<'a>(&'a Value);
+
+impl<'a> Serialize for Json<'a> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match *self.0 {
+            Value::Integer(ref value) if value.to_i128().is_none() && value.to_u128().is_none() => {
+                RawValue::from_string(value.to_string())
+                    .map_err(ser::Error::custom)?
+                    .serialize(serializer)
+            }
+            Value::Float(value) if !value.is_finite() => Err(ser::Error::custom(format!(
+                "{} cannot be represented in JSON",
+                value
+            ))),
+            Value::Array(ref array) => {
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for element in array {
+                    seq.serialize_element(&Json(element))?;
+                }
+                seq.end()
+            }
+            Value::Map(ref map) => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    out.serialize_entry(key, &Json(value))?;
+                }
+                out.end()
+            }
+            ref value => value.serialize(serializer),
+        }
+    }
+}
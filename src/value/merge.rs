@@ -0,0 +1,309 @@
+use crate::error::{Error, Result};
+use crate::path::{Path, PathNode};
+use crate::value::{remove_key, Map, Value};
+
+/// How [`Value::merge_with`] combines two arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayMerge {
+    /// Merge element by element, appending source elements past the end of
+    /// the target. This is what [`Value::merge`] does.
+    #[default]
+    Index,
+    /// Replace the target array with the source array.
+    Replace,
+    /// Append every source element to the target array.
+    Append,
+}
+
+/// How [`Value::merge_with`] treats `Nil` in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NilMerge {
+    /// Keep the target value. This is what [`Value::merge`] does.
+    #[default]
+    Ignore,
+    /// Set the target value to `Nil`.
+    Replace,
+    /// Remove the key from the target map. Array elements and the root are
+    /// set to `Nil` instead.
+    Delete,
+}
+
+/// How [`Value::merge_with`] handles a source value of a different type than
+/// the target value. A `Nil` target is always replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MismatchPolicy {
+    /// Fail with a type error. This is what [`Value::merge`] does.
+    #[default]
+    Error,
+    /// Replace the target value with the source value.
+    SourceWins,
+    /// Keep the target value.
+    TargetWins,
+}
+
+/// The policies used to merge one node, see [`MergeOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeStrategy {
+    arrays: ArrayMerge,
+    nil: NilMerge,
+    mismatch: MismatchPolicy,
+}
+
+impl MergeStrategy {
+    pub fn new() -> Self {
+        MergeStrategy::default()
+    }
+
+    /// Sets how arrays are combined, see [`ArrayMerge`].
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// Sets how `Nil` in the source is treated, see [`NilMerge`].
+    pub fn nil(mut self, nil: NilMerge) -> Self {
+        self.nil = nil;
+        self
+    }
+
+    /// Sets how type mismatches are handled, see [`MismatchPolicy`].
+    pub fn mismatch(mut self, mismatch: MismatchPolicy) -> Self {
+        self.mismatch = mismatch;
+        self
+    }
+}
+
+/// Options for [`Value::merge_with`].
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    strategy: MergeStrategy,
+    overrides: Vec<(Path, MergeStrategy)>,
+}
+
+impl MergeOptions {
+    pub fn new() -> Self {
+        MergeOptions::default()
+    }
+
+    /// Sets the strategy used wherever no override applies.
+    pub fn strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets how arrays are combined by the default strategy.
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.strategy = self.strategy.arrays(arrays);
+        self
+    }
+
+    /// Sets how `Nil` in the source is treated by the default strategy.
+    pub fn nil(mut self, nil: NilMerge) -> Self {
+        self.strategy = self.strategy.nil(nil);
+        self
+    }
+
+    /// Sets how type mismatches are handled by the default strategy.
+    pub fn mismatch(mut self, mismatch: MismatchPolicy) -> Self {
+        self.strategy = self.strategy.mismatch(mismatch);
+        self
+    }
+
+    /// Uses `strategy` for the node at `path` and everything below it.
+    ///
+    /// `path` is compared node by node with the location being merged, so it
+    /// should be singular and use non-negative indices. When several
+    /// overrides apply, the longest one wins.
+    pub fn override_at(mut self, path: Path, strategy: MergeStrategy) -> Self {
+        self.overrides.push((path, strategy));
+        self
+    }
+
+    fn strategy_at(&self, path: &Path) -> MergeStrategy {
+        self.overrides
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.strategy, |(_, strategy)| *strategy)
+    }
+}
+
+impl Value {
+    /// Merges `source` into `self`.
+    ///
+    /// Maps are merged key by key and arrays element by element, `Nil` in the
+    /// source is ignored and values of different types are an error. Use
+    /// [`Value::merge_with`] for other policies.
+    pub fn merge(&mut self, source: Value) -> Result<()> {
+        self.merge_with(source, &MergeOptions::default())
+    }
+
+    /// Like [`Value::merge`], with the policies given by `options`.
+    pub fn merge_with(&mut self, source: Value, options: &MergeOptions) -> Result<()> {
+        self.merge_observed(source, options, &mut |_| {})
+    }
+
+    /// Like [`Value::merge_with`], reporting each change to `observe`.
+    pub(crate) fn merge_observed(
+        &mut self,
+        source: Value,
+        options: &MergeOptions,
+        observe: &mut dyn FnMut(MergeEvent<'_>),
+    ) -> Result<()> {
+        let mut merger = Merger {
+            options,
+            observe,
+            target_path: Path::root(),
+            source_path: Path::root(),
+        };
+        merger.merge(self, source)
+    }
+}
+
+/// A change made by a merge.
+pub(crate) enum MergeEvent<'a> {
+    /// The value at `target` was replaced by the source value at `source`.
+    Replaced { target: &'a Path, source: &'a Path },
+    /// The map entry at `target` was removed.
+    Removed { target: &'a Path },
+}
+
+struct Merger<'a> {
+    options: &'a MergeOptions,
+    observe: &'a mut dyn FnMut(MergeEvent<'_>),
+    // Where the values being merged are, in the target and in the source.
+    target_path: Path,
+    source_path: Path,
+}
+
+impl<'a> Merger<'a> {
+    fn merge(&mut self, target: &mut Value, source: Value) -> Result<()> {
+        let strategy = self.options.strategy_at(&self.target_path);
+        match (target, source) {
+            (target, Value::Nil) => {
+                if strategy.nil != NilMerge::Ignore {
+                    self.replace(target, Value::Nil);
+                }
+            }
+            (target @ Value::Nil, source) => self.replace(target, source),
+            (Value::Map(target), Value::Map(source)) => {
+                for (key, value) in source {
+                    self.push(PathNode::Identifier(key.clone()), PathNode::Identifier(key));
+                    let result = self.merge_entry(target, value);
+                    self.pop();
+                    result?;
+                }
+            }
+            (Value::Array(target), Value::Array(source)) => match strategy.arrays {
+                ArrayMerge::Index => {
+                    for (index, value) in source.into_iter().enumerate() {
+                        let node = PathNode::Index(index as isize);
+                        self.push(node.clone(), node);
+                        let result = match target.get_mut(index) {
+                            Some(slot) => self.merge(slot, value),
+                            None => {
+                                target.push(value);
+                                self.replaced();
+                                Ok(())
+                            }
+                        };
+                        self.pop();
+                        result?;
+                    }
+                }
+                ArrayMerge::Replace => {
+                    *target = source;
+                    self.replaced();
+                }
+                ArrayMerge::Append => {
+                    let len = target.len();
+                    for (index, value) in source.into_iter().enumerate() {
+                        self.push(
+                            PathNode::Index((len + index) as isize),
+                            PathNode::Index(index as isize),
+                        );
+                        target.push(value);
+                        self.replaced();
+                        self.pop();
+                    }
+                }
+            },
+            (target @ Value::Boolean(_), source @ Value::Boolean(_))
+            | (target @ Value::Integer(_), source @ Value::Integer(_))
+            | (target @ Value::Float(_), source @ Value::Float(_))
+            | (target @ Value::String(_), source @ Value::String(_)) => {
+                self.replace(target, source)
+            }
+            (target, source) => match strategy.mismatch {
+                MismatchPolicy::Error => {
+                    return Err(Error::invalid_type(source.unexpected(), expected(target))
+                        .within(&self.target_path));
+                }
+                MismatchPolicy::SourceWins => self.replace(target, source),
+                MismatchPolicy::TargetWins => {}
+            },
+        }
+        Ok(())
+    }
+
+    /// Merges `value` into the map entry named by the last node of the path.
+    fn merge_entry(&mut self, target: &mut Map, value: Value) -> Result<()> {
+        let key = match self.target_path.last() {
+            Some(PathNode::Identifier(key)) => key.clone(),
+            _ => unreachable!("map entries are addressed by key"),
+        };
+        if value == Value::Nil
+            && self.options.strategy_at(&self.target_path).nil == NilMerge::Delete
+        {
+            if remove_key(target, &key).is_some() {
+                (self.observe)(MergeEvent::Removed {
+                    target: &self.target_path,
+                });
+            }
+            return Ok(());
+        }
+        match target.get_mut(&key) {
+            Some(slot) => self.merge(slot, value),
+            None => {
+                target.insert(key, value);
+                self.replaced();
+                Ok(())
+            }
+        }
+    }
+
+    fn replace(&mut self, target: &mut Value, source: Value) {
+        *target = source;
+        self.replaced();
+    }
+
+    /// Reports that the source value at the current location was stored.
+    fn replaced(&mut self) {
+        (self.observe)(MergeEvent::Replaced {
+            target: &self.target_path,
+            source: &self.source_path,
+        });
+    }
+
+    fn push(&mut self, target: PathNode, source: PathNode) {
+        self.target_path.push(target);
+        self.source_path.push(source);
+    }
+
+    fn pop(&mut self) {
+        self.target_path.pop();
+        self.source_path.pop();
+    }
+}
+
+fn expected(target: &Value) -> &'static str {
+    match *target {
+        Value::Nil => "nil",
+        Value::Boolean(_) => "a bool",
+        Value::Integer(_) => "an integer",
+        Value::Float(_) => "a float",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Map(_) => "a map",
+    }
+}
@@ -5,32 +5,69 @@ use std::fmt::Display;
 
 use num_bigint::BigInt;
 use num_traits::{ToPrimitive, Zero};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::error::{Error, Result, Unexpected};
-use crate::path::{Path, PathNode};
+use crate::path::Path;
 use crate::value::ser::ValueSerializer;
 
+pub use self::entry::{Entry, OccupiedEntry, VacantEntry};
+pub use self::merge::{ArrayMerge, MergeOptions, MergeStrategy, MismatchPolicy, NilMerge};
+pub use self::set::{OutOfRange, SetOptions, SetPolicy};
+pub use self::tracked::{Origin, Tracked};
+
 mod de;
+mod entry;
+#[cfg(feature = "json")]
+mod json;
+mod merge;
+mod pointer;
+mod query;
+mod remove;
 mod ser;
+mod set;
+#[cfg(feature = "toml")]
+mod toml;
+mod tracked;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+/// The map type behind [`Value::Map`].
+///
+/// With the `preserve_order` feature, which is on by default, this is an
+/// `IndexMap` that keeps keys in insertion order. Otherwise it is a
+/// `HashMap`.
+#[cfg(feature = "preserve_order")]
+pub type Map = indexmap::IndexMap<String, Value>;
+/// The map type behind [`Value::Map`].
+#[cfg(not(feature = "preserve_order"))]
+pub type Map = HashMap<String, Value>;
+
+/// Removes `key` from `map`, keeping the order of the remaining keys.
+pub(crate) fn remove_key(map: &mut Map, key: &str) -> Option<Value> {
+    #[cfg(feature = "preserve_order")]
+    {
+        map.shift_remove(key)
+    }
+    #[cfg(not(feature = "preserve_order"))]
+    {
+        map.remove(key)
+    }
+}
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Value {
+    #[default]
     Nil,
     Integer(BigInt),
     Float(f64),
     Boolean(bool),
     String(String),
-    Map(HashMap<String, Value>),
+    Map(Map),
     Array(Vec<Value>),
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Nil
-    }
-}
-
 impl Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -83,12 +120,14 @@ impl_from_int_to_value!(i8);
 impl_from_int_to_value!(i16);
 impl_from_int_to_value!(i32);
 impl_from_int_to_value!(i64);
+impl_from_int_to_value!(i128);
 impl_from_int_to_value!(isize);
 
 impl_from_int_to_value!(u8);
 impl_from_int_to_value!(u16);
 impl_from_int_to_value!(u32);
 impl_from_int_to_value!(u64);
+impl_from_int_to_value!(u128);
 impl_from_int_to_value!(usize);
 
 impl From<f64> for Value {
@@ -114,7 +153,7 @@ where
     T: Into<Value>,
 {
     fn from(values: HashMap<String, T>) -> Self {
-        let mut r = HashMap::new();
+        let mut r = Map::new();
 
         for (k, v) in values {
             r.insert(k.clone(), v.into());
@@ -124,6 +163,16 @@ where
     }
 }
 
+#[cfg(feature = "preserve_order")]
+impl<T> From<indexmap::IndexMap<String, T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(values: indexmap::IndexMap<String, T>) -> Self {
+        Value::Map(values.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
 impl<T> From<Vec<T>> for Value
 where
     T: Into<Value>,
@@ -143,9 +192,17 @@ impl TryFrom<Value> for bool {
     type Error = Error;
 
     fn try_from(value: Value) -> Result<Self> {
-        match value {
+        bool::try_from(&value)
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: &'a Value) -> Result<Self> {
+        match *value {
             Value::Boolean(value) => Ok(value),
-            Value::Integer(value) => Ok(value.ne(&Zero::zero())),
+            Value::Integer(ref value) => Ok(value.ne(&Zero::zero())),
             Value::Float(value) => Ok(value != 0.0),
 
             Value::String(ref value) => {
@@ -172,10 +229,18 @@ macro_rules! impl_try_from_value_to_int {
             type Error = Error;
 
             fn try_from(value: Value) -> Result<Self> {
-                match value {
-                    Value::Integer(value) => match value.$ident() {
+                <$ty>::try_from(&value)
+            }
+        }
+
+        impl<'a> TryFrom<&'a Value> for $ty {
+            type Error = Error;
+
+            fn try_from(value: &'a Value) -> Result<Self> {
+                match *value {
+                    Value::Integer(ref value) => match value.$ident() {
                         Some(v) => Ok(v),
-                        None => Err(Error::too_large(value)),
+                        None => Err(Error::too_large(value.clone())),
                     },
                     Value::String(ref s) => {
                         match s.to_lowercase().as_ref() {
@@ -206,11 +271,13 @@ impl_try_from_value_to_int!(i8, to_i8);
 impl_try_from_value_to_int!(i16, to_i16);
 impl_try_from_value_to_int!(i32, to_i32);
 impl_try_from_value_to_int!(i64, to_i64);
+impl_try_from_value_to_int!(i128, to_i128);
 impl_try_from_value_to_int!(isize, to_isize);
 impl_try_from_value_to_int!(u8, to_u8);
 impl_try_from_value_to_int!(u16, to_u16);
 impl_try_from_value_to_int!(u32, to_u32);
 impl_try_from_value_to_int!(u64, to_u64);
+impl_try_from_value_to_int!(u128, to_u128);
 impl_try_from_value_to_int!(usize, to_usize);
 
 macro_rules! impl_try_from_value_to_float {
@@ -219,7 +286,15 @@ macro_rules! impl_try_from_value_to_float {
             type Error = Error;
 
             fn try_from(value: Value) -> Result<Self> {
-                match value {
+                <$ty>::try_from(&value)
+            }
+        }
+
+        impl<'a> TryFrom<&'a Value> for $ty {
+            type Error = Error;
+
+            fn try_from(value: &'a Value) -> Result<Self> {
+                match *value {
                     Value::Float(value) => Ok(value as $ty),
 
                     Value::String(ref s) => {
@@ -238,9 +313,9 @@ macro_rules! impl_try_from_value_to_float {
                         }
                     }
 
-                    Value::Integer(value) => match value.$ident() {
+                    Value::Integer(ref value) => match value.$ident() {
                         Some(v) => Ok(v),
-                        None => Err(Error::too_large(value)),
+                        None => Err(Error::too_large(value.clone())),
                     },
                     Value::Boolean(value) => Ok(if value { 1.0 } else { 0.0 }),
 
@@ -303,7 +378,7 @@ impl TryFrom<Value> for HashMap<String, Value> {
 
     fn try_from(value: Value) -> Result<Self> {
         match value {
-            Value::Map(value) => Ok(value),
+            Value::Map(value) => Ok(value.into_iter().collect()),
 
             // Cannot convert
             Value::Float(value) => Err(Error::invalid_type(Unexpected::Float(value), "a map")),
@@ -316,6 +391,72 @@ impl TryFrom<Value> for HashMap<String, Value> {
     }
 }
 
+#[cfg(feature = "preserve_order")]
+impl TryFrom<Value> for indexmap::IndexMap<String, Value> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Map(value) => Ok(value),
+
+            // Cannot convert
+            ref value => Err(Error::invalid_type(value.unexpected(), "a map")),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = Error;
+
+    fn try_from(value: &'a Value) -> Result<Self> {
+        match *value {
+            Value::String(ref value) => Ok(value),
+
+            // Cannot borrow as a string
+            ref value => Err(Error::invalid_type(value.unexpected(), "a string")),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a BigInt {
+    type Error = Error;
+
+    fn try_from(value: &'a Value) -> Result<Self> {
+        match *value {
+            Value::Integer(ref value) => Ok(value),
+
+            // Cannot borrow as an integer
+            ref value => Err(Error::invalid_type(value.unexpected(), "an integer")),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a [Value] {
+    type Error = Error;
+
+    fn try_from(value: &'a Value) -> Result<Self> {
+        match *value {
+            Value::Array(ref value) => Ok(value),
+
+            // Cannot borrow as an array
+            ref value => Err(Error::invalid_type(value.unexpected(), "an array")),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a Map {
+    type Error = Error;
+
+    fn try_from(value: &'a Value) -> Result<Self> {
+        match *value {
+            Value::Map(ref value) => Ok(value),
+
+            // Cannot borrow as a map
+            ref value => Err(Error::invalid_type(value.unexpected(), "a map")),
+        }
+    }
+}
+
 impl TryFrom<Value> for () {
     type Error = Error;
 
@@ -325,140 +466,94 @@ impl TryFrom<Value> for () {
 }
 
 impl Value {
-    pub fn merge(&mut self, source: Value) -> Result<()> {
-        match self {
-            Value::Boolean(v_t) => match source {
-                Value::Boolean(v_s) => {
-                    *v_t = v_s;
-                    Ok(())
-                }
+    /// Describes this value for type errors.
+    pub(crate) fn unexpected(&self) -> Unexpected {
+        match *self {
+            Value::Nil => Unexpected::Unit,
+            Value::Boolean(b) => Unexpected::Bool(b),
+            Value::Integer(ref i) => Unexpected::Integer(i.clone()),
+            Value::Float(f) => Unexpected::Float(f),
+            Value::String(ref s) => Unexpected::Str(s.clone()),
+            Value::Array(_) => Unexpected::Array,
+            Value::Map(_) => Unexpected::Map,
+        }
+    }
 
-                // Cannot convert
-                Value::Float(value) => Err(Error::invalid_type(Unexpected::Float(value), "a bool")),
-                Value::String(value) => Err(Error::invalid_type(Unexpected::Str(value), "a bool")),
-                Value::Integer(value) => {
-                    Err(Error::invalid_type(Unexpected::Integer(value), "a bool"))
-                }
-                Value::Nil => Ok(()),
-                Value::Array(_) => Err(Error::invalid_type(Unexpected::Array, "a bool")),
-                Value::Map(_) => Err(Error::invalid_type(Unexpected::Map, "a bool")),
-            },
-            Value::Integer(v_t) => match source {
-                Value::Integer(v_s) => {
-                    *v_t = v_s;
-                    Ok(())
-                }
+    /// Returns `true` if the value is `Nil`.
+    pub fn is_nil(&self) -> bool {
+        matches!(*self, Value::Nil)
+    }
 
-                // Cannot convert
-                Value::Float(value) => {
-                    Err(Error::invalid_type(Unexpected::Float(value), "a integer"))
-                }
-                Value::String(value) => {
-                    Err(Error::invalid_type(Unexpected::Str(value), "a integer"))
-                }
-                Value::Boolean(value) => {
-                    Err(Error::invalid_type(Unexpected::Bool(value), "a integer"))
-                }
-                Value::Nil => Ok(()),
-                Value::Array(_) => Err(Error::invalid_type(Unexpected::Array, "a integer")),
-                Value::Map(_) => Err(Error::invalid_type(Unexpected::Map, "a integer")),
-            },
-            Value::Float(v_t) => match source {
-                Value::Float(v_s) => {
-                    *v_t = v_s;
-                    Ok(())
-                }
+    /// Returns the boolean if the value is a `Boolean`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Boolean(value) => Some(value),
+            _ => None,
+        }
+    }
 
-                // Cannot convert
-                Value::Integer(value) => {
-                    Err(Error::invalid_type(Unexpected::Integer(value), "a float"))
-                }
-                Value::String(value) => Err(Error::invalid_type(Unexpected::Str(value), "a float")),
-                Value::Boolean(value) => {
-                    Err(Error::invalid_type(Unexpected::Bool(value), "a float"))
-                }
-                Value::Nil => Ok(()),
-                Value::Array(_) => Err(Error::invalid_type(Unexpected::Array, "a float")),
-                Value::Map(_) => Err(Error::invalid_type(Unexpected::Map, "a float")),
-            },
-            Value::String(v_t) => match source {
-                Value::String(v_s) => {
-                    *v_t = v_s;
-                    Ok(())
-                }
+    /// Borrows the integer if the value is an `Integer`.
+    pub fn as_integer(&self) -> Option<&BigInt> {
+        match *self {
+            Value::Integer(ref value) => Some(value),
+            _ => None,
+        }
+    }
 
-                // Cannot convert
-                Value::Integer(value) => {
-                    Err(Error::invalid_type(Unexpected::Integer(value), "a string"))
-                }
-                Value::Float(value) => {
-                    Err(Error::invalid_type(Unexpected::Float(value), "a string"))
-                }
-                Value::Boolean(value) => {
-                    Err(Error::invalid_type(Unexpected::Bool(value), "a string"))
-                }
-                Value::Nil => Ok(()),
-                Value::Array(_) => Err(Error::invalid_type(Unexpected::Array, "a string")),
-                Value::Map(_) => Err(Error::invalid_type(Unexpected::Map, "a string")),
-            },
-            Value::Nil => match source {
-                Value::Nil => Ok(()),
-                _ => {
-                    *self = source;
-                    Ok(())
-                }
-            },
-            Value::Map(v_t) => match source {
-                Value::Map(v_s) => {
-                    for (k, v) in v_s {
-                        match v_t.get_mut(&k) {
-                            Some(j) => Value::merge(j, v)?,
-                            None => {
-                                v_t.insert(k, v);
-                            }
-                        }
-                    }
-                    Ok(())
-                }
-                // Cannot convert
-                Value::Integer(value) => {
-                    Err(Error::invalid_type(Unexpected::Integer(value), "a map"))
-                }
-                Value::Float(value) => Err(Error::invalid_type(Unexpected::Float(value), "a map")),
-                Value::Boolean(value) => Err(Error::invalid_type(Unexpected::Bool(value), "a map")),
-                Value::String(value) => Err(Error::invalid_type(Unexpected::Str(value), "a map")),
-                Value::Nil => Ok(()),
-                Value::Array(_) => Err(Error::invalid_type(Unexpected::Array, "a map")),
-            },
-            Value::Array(v_t) => match source {
-                Value::Array(v_s) => {
-                    for (index, v) in v_s.into_iter().enumerate() {
-                        match v_t.get_mut(index) {
-                            Some(j) => Value::merge(j, v)?,
-                            None => {
-                                v_t.push(v);
-                            }
-                        }
-                    }
-                    Ok(())
-                }
-                // Cannot convert
-                Value::Integer(value) => {
-                    Err(Error::invalid_type(Unexpected::Integer(value), "a array"))
-                }
-                Value::Float(value) => {
-                    Err(Error::invalid_type(Unexpected::Float(value), "a array"))
-                }
-                Value::Boolean(value) => {
-                    Err(Error::invalid_type(Unexpected::Bool(value), "a array"))
-                }
-                Value::String(value) => Err(Error::invalid_type(Unexpected::Str(value), "a array")),
-                Value::Nil => Ok(()),
-                Value::Map(_) => Err(Error::invalid_type(Unexpected::Map, "a array")),
-            },
+    /// Returns the float if the value is a `Float`.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Borrows the string if the value is a `String`.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Borrows the map if the value is a `Map`.
+    pub fn as_map(&self) -> Option<&Map> {
+        match *self {
+            Value::Map(ref value) => Some(value),
+            _ => None,
         }
     }
 
+    /// Mutably borrows the map if the value is a `Map`.
+    pub fn as_map_mut(&mut self) -> Option<&mut Map> {
+        match *self {
+            Value::Map(ref mut value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Borrows the elements if the value is an `Array`.
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match *self {
+            Value::Array(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Mutably borrows the elements if the value is an `Array`.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::Array(ref mut value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Sets `input_value` at `path`, creating missing maps and arrays on the
+    /// way, and returns the previous value.
+    ///
+    /// If `path` is not singular (see [`Path::is_singular`]), every existing
+    /// match is replaced instead and the previous values are returned as an
+    /// array.
     pub fn set<P, IntoValue, IntoErr>(
         &mut self,
         path: P,
@@ -469,55 +564,28 @@ impl Value {
         IntoValue: Into<Value>,
         IntoErr: Into<Error>,
     {
-        let input_value = input_value.into();
-        let path = path.try_into().map_err(|err| err.into())?;
-        unsafe {
-            let mut parent = self as *mut Value;
-            let mut target = self as *mut Value;
-            let v = vec![1];
-            for sub_path in path.iter() {
-                match *sub_path {
-                    PathNode::Identifier(ref ident) => match &mut *parent {
-                        Value::Map(parent_map) => {
-                            target = parent_map.entry(ident.clone()).or_default();
-                            parent = target;
-                        }
-
-                        _ => {
-                            *parent = HashMap::<String, Value>::new().into();
-                            if let Value::Map(parent_map) = &mut *parent {
-                                target = parent_map.entry(ident.clone()).or_default();
-                                parent = target;
-                            } else {
-                                unreachable!()
-                            }
-                        }
-                    },
-                    PathNode::Index(index) => match &mut *parent {
-                        Value::Array(parent_array) => {
-                            target = Value::get_array_slot(parent_array, index);
-                            parent = target;
-                        }
-
-                        _ => {
-                            *parent = vec![Value::default()].into();
-                            if let Value::Array(parent_array) = &mut *parent {
-                                target = Value::get_array_slot(parent_array, index);
-                                parent = target;
-                            } else {
-                                unreachable!()
-                            }
-                        }
-                    },
-                }
-            }
+        self.set_with(path, input_value, &SetOptions::default())
+    }
 
-            let result = (*target).clone();
-            *target = input_value;
-            Ok(result)
+    /// Returns a mutable reference to the value at `path`, or to the first
+    /// match for a non-singular path.
+    pub fn get_mut<P, IntoErr>(&mut self, path: P) -> Result<Option<&mut Value>, Error>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        if path.is_singular() {
+            return Ok(self.walk_mut(&path));
         }
+        let concrete = self.query(&path)?.next().map(|(concrete, _)| concrete);
+        Ok(concrete.and_then(move |concrete| self.walk_mut(&concrete)))
     }
 
+    /// Converts the value at `path` into `T`.
+    ///
+    /// Returns `Ok(None)` if nothing matches; for a non-singular path the
+    /// first match is used.
     pub fn get<T, P, IntoErr>(&self, path: P) -> Result<Option<T>, Error>
     where
         T: std::convert::TryFrom<Value, Error = IntoErr>,
@@ -525,63 +593,81 @@ impl Value {
         IntoErr: Into<Error>,
     {
         let path = path.try_into().map_err(|err| err.into())?;
-        let value = path
-            .iter()
-            .scan(self, |value, child_path| {
-                let result = match *child_path {
-                    PathNode::Identifier(ref id) => match **value {
-                        Value::Map(ref map) => map.get(id),
-                        _ => None,
-                    },
-
-                    PathNode::Index(index) => match **value {
-                        Value::Array(ref array) => {
-                            let index = Value::map_index(index, array.len());
-
-                            if index >= array.len() {
-                                None
-                            } else {
-                                Some(&array[index])
-                            }
-                        }
-                        _ => None,
-                    },
-                };
-                if let Some(v) = result {
-                    *value = v;
-                }
-                result
-            })
-            .last();
-        match value {
+        match self.select_first_at(&path) {
             None => Ok(None),
-            Some(value) => Ok(Some(
-                Value::try_into(value.clone()).map_err(|err: IntoErr| err.into())?,
+            Some((at, value)) => Ok(Some(
+                Value::try_into(value.clone()).map_err(|err: IntoErr| err.into().within(&at))?,
             )),
         }
     }
 
-    fn map_index(index: isize, len: usize) -> usize {
-        if index >= 0 {
-            index as usize
-        } else {
-            len - (index.abs() as usize)
+    /// Returns a reference to the value at `path`, or to the first match of a
+    /// non-singular path.
+    pub fn get_ref<P, IntoErr>(&self, path: P) -> Result<Option<&Value>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        Ok(self.select_first(&path))
+    }
+
+    /// Like [`Value::get`], but converts from a reference so that nothing is
+    /// cloned. `T` can borrow from `self`, e.g. `&str`, `&[Value]` or
+    /// `&Map`.
+    pub fn get_borrowed<'a, T, P, IntoErr>(&'a self, path: P) -> Result<Option<T>>
+    where
+        T: TryFrom<&'a Value, Error = Error>,
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        match self.select_first_at(&path) {
+            None => Ok(None),
+            Some((at, value)) => Ok(Some(T::try_from(value).map_err(|err| err.within(&at))?)),
         }
     }
 
-    unsafe fn get_array_slot(array: &mut Vec<Value>, index: isize) -> *mut Value {
-        let index = Value::map_index(index, array.len());
-        match array.get_mut(index) {
-            Some(v) => v,
-            None => {
-                array.insert(index, Value::default());
-                match array.get_mut(index) {
-                    Some(v) => v,
-                    None => unreachable!(),
-                }
-            }
+    /// Borrows the string at `path`. Fails if the value is not a string.
+    pub fn get_str<P, IntoErr>(&self, path: P) -> Result<Option<&str>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        self.get_borrowed(path)
+    }
+
+    /// Deserializes the value at `path` into any `T: Deserialize`, e.g. a
+    /// config struct. Only the addressed subtree is cloned.
+    ///
+    /// Returns `Ok(None)` if nothing matches; for a non-singular path the
+    /// first match is used.
+    pub fn get_as<T, P, IntoErr>(&self, path: P) -> Result<Option<T>>
+    where
+        T: DeserializeOwned,
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        match self.select_first_at(&path) {
+            None => Ok(None),
+            Some((at, value)) => Ok(Some(
+                T::deserialize(value.clone()).map_err(|err| err.within(&at))?,
+            )),
         }
     }
+
+    /// Serializes `input` with [`to_value`] and sets the result at `path`, see
+    /// [`Value::set`].
+    pub fn set_from<T, P, IntoErr>(&mut self, path: P, input: &T) -> Result<Value>
+    where
+        T: Serialize + ?Sized,
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let input_value = to_value(input)?;
+        self.set(path, input_value)
+    }
 }
 
 pub fn to_value<T>(from: T) -> Result<Value>
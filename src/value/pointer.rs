@@ -0,0 +1,54 @@
+use crate::error::Result;
+use crate::path::{Path, PathNode};
+use crate::value::Value;
+
+impl Value {
+    /// Looks up a value by RFC 6901 JSON Pointer, e.g. `/a/0/b~1c`.
+    ///
+    /// A numeric segment is an array index when it meets an array and a map
+    /// key when it meets a map. Fails only if `pointer` is malformed.
+    pub fn pointer(&self, pointer: &str) -> Result<Option<&Value>> {
+        let path = Path::from_json_pointer(pointer)?;
+        let mut value = self;
+        for node in path.iter() {
+            value = match pointer_child(node, value) {
+                Some(child) => child,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(value))
+    }
+
+    /// Mutable counterpart of [`Value::pointer`].
+    pub fn pointer_mut(&mut self, pointer: &str) -> Result<Option<&mut Value>> {
+        let path = Path::from_json_pointer(pointer)?;
+        let mut value = self;
+        for node in path.iter() {
+            value = match pointer_child_mut(node, value) {
+                Some(child) => child,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(value))
+    }
+}
+
+fn pointer_child<'a>(node: &PathNode, value: &'a Value) -> Option<&'a Value> {
+    match (node, value) {
+        (PathNode::Identifier(key), Value::Map(map)) => map.get(key),
+        (PathNode::Index(index), Value::Map(map)) => map.get(&index.to_string()),
+        (PathNode::Append, Value::Map(map)) => map.get("-"),
+        (PathNode::Index(index), Value::Array(array)) => array.get(*index as usize),
+        _ => None,
+    }
+}
+
+fn pointer_child_mut<'a>(node: &PathNode, value: &'a mut Value) -> Option<&'a mut Value> {
+    match (node, value) {
+        (PathNode::Identifier(key), Value::Map(map)) => map.get_mut(key),
+        (PathNode::Index(index), Value::Map(map)) => map.get_mut(&index.to_string()),
+        (PathNode::Append, Value::Map(map)) => map.get_mut("-"),
+        (PathNode::Index(index), Value::Array(array)) => array.get_mut(*index as usize),
+        _ => None,
+    }
+}
@@ -0,0 +1,373 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryInto;
+
+use num_traits::ToPrimitive;
+
+use crate::error::{Error, Result};
+use crate::path::{CompareOp, FilterExpr, Literal, Operand, Path, PathNode};
+use crate::value::Value;
+
+impl Value {
+    /// Returns every `(path, value)` pair matched by `path`.
+    ///
+    /// Unlike [`Value::get`], the path may contain wildcards (`*`),
+    /// recursive descents (`**`), slices and filters. The returned paths are concrete, negative
+    /// indices are resolved against the array length.
+    pub fn query<P, IntoErr>(&self, path: P) -> Result<impl Iterator<Item = (Path, &Value)>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        let mut matches = Vec::new();
+        select(self, &path, &mut Path::root(), &mut |path, value| {
+            matches.push((path.clone(), value));
+            true
+        });
+        Ok(matches.into_iter())
+    }
+
+    /// Returns the first value matched by `path`, with its concrete path.
+    pub(crate) fn select_first_at(&self, path: &Path) -> Option<(Path, &Value)> {
+        let mut first = None;
+        select(self, path, &mut Path::root(), &mut |path, value| {
+            first = Some((path.clone(), value));
+            false
+        });
+        first
+    }
+
+    /// Returns the first value matched by `path`.
+    pub(crate) fn select_first(&self, path: &Path) -> Option<&Value> {
+        let mut first = None;
+        select(self, path, &mut Path::root(), &mut |_, value| {
+            first = Some(value);
+            false
+        });
+        first
+    }
+
+    /// Replaces every value matched by `path` and returns the old values in
+    /// match order. Missing locations are not created.
+    pub(crate) fn replace_matches(&mut self, path: &Path, value: &Value) -> Vec<Value> {
+        let mut replaced = Vec::new();
+        select_mut(self, path, &mut |target| {
+            replaced.push(std::mem::replace(target, value.clone()));
+        });
+        replaced
+    }
+
+    pub(crate) fn map_index(index: isize, len: usize) -> Option<usize> {
+        if index >= 0 {
+            Some(index as usize)
+        } else {
+            len.checked_sub(index.unsigned_abs())
+        }
+    }
+}
+
+/// Resolves a slice against an array of `len` elements with the same
+/// semantics as Python's `list[start:end:step]`.
+fn slice_indices(start: Option<isize>, end: Option<isize>, step: isize, len: usize) -> Vec<usize> {
+    let len = len as isize;
+    let resolve = |bound: isize, lower: isize, upper: isize| {
+        let bound = if bound < 0 { bound + len } else { bound };
+        bound.max(lower).min(upper)
+    };
+
+    let mut indices = Vec::new();
+    if step > 0 {
+        let mut i = start.map_or(0, |b| resolve(b, 0, len));
+        let end = end.map_or(len, |b| resolve(b, 0, len));
+        while i < end {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    } else if step < 0 {
+        let mut i = start.map_or(len - 1, |b| resolve(b, -1, len - 1));
+        let end = end.map_or(-1, |b| resolve(b, -1, len - 1));
+        while i > end {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+    indices
+}
+
+/// Walks `value` along `nodes`, calling `visit` for each match until it
+/// returns `false`. Returns `false` once the walk has been stopped.
+fn select<'a, F>(value: &'a Value, nodes: &[PathNode], current: &mut Path, visit: &mut F) -> bool
+where
+    F: FnMut(&Path, &'a Value) -> bool,
+{
+    let (node, rest) = match nodes.split_first() {
+        Some(split) => split,
+        None => return visit(current, value),
+    };
+
+    match *node {
+        PathNode::Identifier(ref key) => match *value {
+            Value::Map(ref map) => match map.get(key) {
+                Some(child) => select_child(child, node.clone(), rest, current, visit),
+                None => true,
+            },
+            _ => true,
+        },
+
+        PathNode::Index(index) => match *value {
+            Value::Array(ref array) => match Value::map_index(index, array.len()) {
+                Some(index) if index < array.len() => select_child(
+                    &array[index],
+                    PathNode::Index(index as isize),
+                    rest,
+                    current,
+                    visit,
+                ),
+                _ => true,
+            },
+            _ => true,
+        },
+
+        PathNode::Slice { start, end, step } => match *value {
+            Value::Array(ref array) => slice_indices(start, end, step, array.len())
+                .into_iter()
+                .all(|index| {
+                    select_child(
+                        &array[index],
+                        PathNode::Index(index as isize),
+                        rest,
+                        current,
+                        visit,
+                    )
+                }),
+            _ => true,
+        },
+
+        PathNode::Filter(ref expr) => select_children(value, rest, current, visit, &|child| {
+            eval_filter(expr, child)
+        }),
+
+        PathNode::Wildcard => select_children(value, rest, current, visit, &|_| true),
+
+        PathNode::Recursive => {
+            select(value, rest, current, visit)
+                && select_children(value, nodes, current, visit, &|_| true)
+        }
+
+        // Markers for new slots never match an existing value.
+        PathNode::Append | PathNode::InsertBefore(_) => true,
+    }
+}
+
+fn select_child<'a, F>(
+    child: &'a Value,
+    node: PathNode,
+    rest: &[PathNode],
+    current: &mut Path,
+    visit: &mut F,
+) -> bool
+where
+    F: FnMut(&Path, &'a Value) -> bool,
+{
+    current.push(node);
+    let more = select(child, rest, current, visit);
+    current.pop();
+    more
+}
+
+/// Selects along `nodes` from each child of `value` accepted by `keep`.
+fn select_children<'a, F>(
+    value: &'a Value,
+    nodes: &[PathNode],
+    current: &mut Path,
+    visit: &mut F,
+    keep: &dyn Fn(&Value) -> bool,
+) -> bool
+where
+    F: FnMut(&Path, &'a Value) -> bool,
+{
+    match *value {
+        Value::Map(ref map) => map
+            .iter()
+            .filter(|(_, child)| keep(child))
+            .all(|(key, child)| {
+                select_child(
+                    child,
+                    PathNode::Identifier(key.clone()),
+                    nodes,
+                    current,
+                    visit,
+                )
+            }),
+        Value::Array(ref array) => array
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| keep(child))
+            .all(|(index, child)| {
+                select_child(
+                    child,
+                    PathNode::Index(index as isize),
+                    nodes,
+                    current,
+                    visit,
+                )
+            }),
+        _ => true,
+    }
+}
+
+/// Mutable counterpart of [`select`]. Descendants are visited before their
+/// ancestors, so replacing a value never affects which values are visited.
+fn select_mut<F>(value: &mut Value, nodes: &[PathNode], visit: &mut F)
+where
+    F: FnMut(&mut Value),
+{
+    let (node, rest) = match nodes.split_first() {
+        Some(split) => split,
+        None => return visit(value),
+    };
+
+    match *node {
+        PathNode::Identifier(ref key) => {
+            if let Value::Map(ref mut map) = *value {
+                if let Some(child) = map.get_mut(key) {
+                    select_mut(child, rest, visit);
+                }
+            }
+        }
+
+        PathNode::Index(index) => {
+            if let Value::Array(ref mut array) = *value {
+                let len = array.len();
+                if let Some(child) = Value::map_index(index, len).and_then(|i| array.get_mut(i)) {
+                    select_mut(child, rest, visit);
+                }
+            }
+        }
+
+        PathNode::Slice { start, end, step } => {
+            if let Value::Array(ref mut array) = *value {
+                for index in slice_indices(start, end, step, array.len()) {
+                    select_mut(&mut array[index], rest, visit);
+                }
+            }
+        }
+
+        PathNode::Filter(ref expr) => {
+            select_children_mut(value, rest, visit, &|child| eval_filter(expr, child))
+        }
+
+        PathNode::Wildcard => select_children_mut(value, rest, visit, &|_| true),
+
+        PathNode::Recursive => {
+            select_children_mut(value, nodes, visit, &|_| true);
+            select_mut(value, rest, visit);
+        }
+
+        PathNode::Append | PathNode::InsertBefore(_) => {}
+    }
+}
+
+fn select_children_mut<F>(
+    value: &mut Value,
+    nodes: &[PathNode],
+    visit: &mut F,
+    keep: &dyn Fn(&Value) -> bool,
+) where
+    F: FnMut(&mut Value),
+{
+    match *value {
+        Value::Map(ref mut map) => map
+            .values_mut()
+            .filter(|child| keep(child))
+            .for_each(|child| select_mut(child, nodes, visit)),
+        Value::Array(ref mut array) => array
+            .iter_mut()
+            .filter(|child| keep(child))
+            .for_each(|child| select_mut(child, nodes, visit)),
+        _ => {}
+    }
+}
+
+/// Evaluates a filter predicate against one candidate element.
+fn eval_filter(expr: &FilterExpr, value: &Value) -> bool {
+    match *expr {
+        FilterExpr::Or(ref left, ref right) => {
+            eval_filter(left, value) || eval_filter(right, value)
+        }
+        FilterExpr::And(ref left, ref right) => {
+            eval_filter(left, value) && eval_filter(right, value)
+        }
+        FilterExpr::Not(ref expr) => !eval_filter(expr, value),
+        FilterExpr::Exists(ref path) => value.select_first(path).is_some(),
+        FilterExpr::Compare(ref left, op, ref right) => {
+            // A comparison involving a missing value never holds.
+            match (eval_operand(left, value), eval_operand(right, value)) {
+                (Some(left), Some(right)) => compare(&left, op, &right),
+                _ => false,
+            }
+        }
+    }
+}
+
+fn eval_operand<'a>(operand: &Operand, value: &'a Value) -> Option<Cow<'a, Value>> {
+    match *operand {
+        Operand::Current(ref path) => value.select_first(path).map(Cow::Borrowed),
+        Operand::Literal(ref literal) => Some(Cow::Owned(match *literal {
+            Literal::Nil => Value::Nil,
+            Literal::Boolean(b) => Value::Boolean(b),
+            Literal::Integer(ref i) => Value::Integer(i.clone()),
+            Literal::Float(f) => Value::Float(f),
+            Literal::String(ref s) => Value::String(s.clone()),
+        })),
+    }
+}
+
+/// Compares two values. Numbers compare across integers and floats, strings
+/// compare lexicographically, and other values only support `==` and `!=`.
+fn compare(left: &Value, op: CompareOp, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => Some(l.cmp(r)),
+        (Value::Integer(l), Value::Float(r)) => l.to_f64().and_then(|l| l.partial_cmp(r)),
+        (Value::Float(l), Value::Integer(r)) => r.to_f64().and_then(|r| l.partial_cmp(&r)),
+        (Value::Float(l), Value::Float(r)) => l.partial_cmp(r),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    };
+
+    match (op, ordering) {
+        (CompareOp::Eq, Some(ordering)) => ordering == Ordering::Equal,
+        (CompareOp::Eq, None) => left == right,
+        (CompareOp::Ne, Some(ordering)) => ordering != Ordering::Equal,
+        (CompareOp::Ne, None) => left != right,
+        (CompareOp::Lt, Some(ordering)) => ordering == Ordering::Less,
+        (CompareOp::Le, Some(ordering)) => ordering != Ordering::Greater,
+        (CompareOp::Gt, Some(ordering)) => ordering == Ordering::Greater,
+        (CompareOp::Ge, Some(ordering)) => ordering != Ordering::Less,
+        (_, None) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::slice_indices;
+
+    #[test]
+    fn test_slice_indices() {
+        assert_eq!(slice_indices(Some(1), Some(-1), 1, 5), vec![1, 2, 3]);
+        assert_eq!(slice_indices(None, None, 2, 5), vec![0, 2, 4]);
+        assert_eq!(slice_indices(None, None, -1, 3), vec![2, 1, 0]);
+        assert_eq!(slice_indices(Some(-2), None, -1, 5), vec![3, 2, 1, 0]);
+        assert_eq!(slice_indices(Some(-10), Some(10), 1, 3), vec![0, 1, 2]);
+        assert_eq!(slice_indices(Some(3), Some(1), 1, 5), Vec::<usize>::new());
+        assert_eq!(slice_indices(None, None, 0, 5), Vec::<usize>::new());
+        assert_eq!(slice_indices(None, None, 1, 0), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,174 @@
+use std::convert::TryInto;
+
+use crate::error::{Error, Result};
+use crate::path::{Path, PathNode};
+use crate::value::{remove_key, Value};
+
+impl Value {
+    /// Removes the value at `path` from its parent map or array and returns it.
+    ///
+    /// Removing the root leaves `Nil` behind. If `path` is not singular, every
+    /// match is removed and the removed values are returned as an array, in
+    /// match order. Returns `Ok(None)` if nothing matches.
+    pub fn remove<P, IntoErr>(&mut self, path: P) -> Result<Option<Value>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        if path.is_singular() {
+            return Ok(self.remove_at(&path));
+        }
+
+        let mut matches: Vec<(usize, Path)> = self
+            .query(&path)?
+            .map(|(path, _)| path)
+            .enumerate()
+            .collect();
+        if matches.is_empty() {
+            return Ok(None);
+        }
+
+        // Remove later siblings and descendants first, so that the remaining
+        // paths stay valid while removing.
+        matches.sort_by(|(_, a), (_, b)| b.cmp_concrete(a));
+        let mut removed: Vec<(usize, Value)> = matches
+            .into_iter()
+            .filter_map(|(order, path)| self.remove_at(&path).map(|value| (order, value)))
+            .collect();
+        removed.sort_by_key(|(order, _)| *order);
+        Ok(Some(Value::Array(
+            removed.into_iter().map(|(_, value)| value).collect(),
+        )))
+    }
+
+    /// Replaces the value at `path` with `Nil` and returns it.
+    ///
+    /// If `path` is not singular, every match is replaced and the previous
+    /// values are returned as an array. Returns `Ok(None)` if nothing matches.
+    pub fn take<P, IntoErr>(&mut self, path: P) -> Result<Option<Value>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        if path.is_singular() {
+            return Ok(self.walk_mut(&path).map(std::mem::take));
+        }
+
+        let taken = self.replace_matches(&path, &Value::Nil);
+        if taken.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(Value::Array(taken)))
+        }
+    }
+
+    /// Like [`Value::remove`] for a singular path, and then also removes each
+    /// parent map or array that was left empty.
+    pub fn remove_pruned<P, IntoErr>(&mut self, path: P) -> Result<Option<Value>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        if !path.is_singular() {
+            return Err(Error::not_singular(path));
+        }
+
+        let removed = self.remove_at(&path);
+        if removed.is_some() {
+            for depth in (1..path.len()).rev() {
+                let parent = &path[..depth];
+                if !self.walk(parent).is_some_and(Value::is_empty_container) {
+                    break;
+                }
+                self.remove_at(parent);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Removes every descendant for which `f` returns `false`.
+    ///
+    /// Children are visited before their parents, so `f` sees containers after
+    /// their own children have been filtered, e.g.
+    /// `value.retain(|_, v| !v.is_empty_container())` prunes empty branches.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Path, &Value) -> bool,
+    {
+        retain(self, &mut Path::root(), &mut f);
+    }
+
+    /// Returns `true` for a map or an array without elements.
+    pub fn is_empty_container(&self) -> bool {
+        match *self {
+            Value::Map(ref map) => map.is_empty(),
+            Value::Array(ref array) => array.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Removes the value at a singular path.
+    fn remove_at(&mut self, path: &[PathNode]) -> Option<Value> {
+        let (last, parent) = match path.split_last() {
+            Some(split) => split,
+            None => return Some(std::mem::take(self)),
+        };
+        match (last, self.walk_mut(parent)?) {
+            (PathNode::Identifier(key), Value::Map(map)) => remove_key(map, key),
+            (PathNode::Index(index), Value::Array(array)) => {
+                let index = Value::map_index(*index, array.len())?;
+                if index < array.len() {
+                    Some(array.remove(index))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn retain<F>(value: &mut Value, current: &mut Path, f: &mut F)
+where
+    F: FnMut(&Path, &Value) -> bool,
+{
+    match *value {
+        Value::Map(ref mut map) => {
+            let keys: Vec<String> = map.keys().cloned().collect();
+            for key in keys {
+                current.push(PathNode::Identifier(key.clone()));
+                let keep = match map.get_mut(&key) {
+                    Some(child) => {
+                        retain(child, current, f);
+                        f(current, child)
+                    }
+                    None => true,
+                };
+                current.pop();
+                if !keep {
+                    remove_key(map, &key);
+                }
+            }
+        }
+        Value::Array(ref mut array) => {
+            let mut index = 0;
+            let mut original = 0;
+            while index < array.len() {
+                current.push(PathNode::Index(original as isize));
+                retain(&mut array[index], current, f);
+                let keep = f(current, &array[index]);
+                current.pop();
+                if keep {
+                    index += 1;
+                } else {
+                    array.remove(index);
+                }
+                original += 1;
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,14 +1,66 @@
 use std::fmt::Display;
 
+use num_traits::ToPrimitive;
 use serde::ser;
 
 use crate::error::{Error, Result};
 use crate::path;
 use crate::value::Value;
 
+impl ser::Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match *self {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Boolean(v) => serializer.serialize_bool(v),
+            Value::Integer(ref v) => {
+                // Use the narrowest primitive that holds the value, so formats
+                // without 128-bit support still work for ordinary integers.
+                if let Some(v) = v.to_i64() {
+                    serializer.serialize_i64(v)
+                } else if let Some(v) = v.to_u64() {
+                    serializer.serialize_u64(v)
+                } else if let Some(v) = v.to_i128() {
+                    serializer.serialize_i128(v)
+                } else if let Some(v) = v.to_u128() {
+                    serializer.serialize_u128(v)
+                } else {
+                    Err(ser::Error::custom(format!(
+                        "integer {} is out of the 128-bit range",
+                        v
+                    )))
+                }
+            }
+            Value::Float(v) => serializer.serialize_f64(v),
+            Value::String(ref v) => serializer.serialize_str(v),
+            Value::Array(ref array) => {
+                use serde::ser::SerializeSeq;
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for element in array {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            Value::Map(ref map) => {
+                use serde::ser::SerializeMap;
+                let mut m = serializer.serialize_map(Some(map.len()))?;
+                for (k, v) in map {
+                    m.serialize_entry(k, v)?;
+                }
+                m.end()
+            }
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct ValueSerializer {
-    keys: Vec<(String, Option<usize>)>,
+    // Location of the value being serialized.
+    path: path::Path,
+    // Next element index of each sequence being serialized, innermost last.
+    indices: Vec<usize>,
     pub output: Value,
 }
 
@@ -17,67 +69,56 @@ impl ValueSerializer {
     where
         T: Into<Value> + Display,
     {
-        let key = match self.last_key_index_pair() {
-            Some((key, Some(index))) => format!("{}[{}]", key, index),
-            Some((key, None)) => key.to_string(),
-            None => {
-                self.output = value.into();
-                return Ok(());
-            }
-        };
-        let path: path::Path = key.parse()?;
-        self.output.set(path, value.into())?;
-        Ok(())
-    }
-
-    fn last_key_index_pair(&self) -> Option<(&str, Option<usize>)> {
-        let len = self.keys.len();
-        if len > 0 {
-            self.keys
-                .get(len - 1)
-                .map(|&(ref key, opt)| (key.as_str(), opt))
-        } else {
-            None
-        }
+        self.write(value.into())
     }
 
-    fn inc_last_key_index(&mut self) -> Result<()> {
-        let len = self.keys.len();
-        if len > 0 {
-            self.keys
-                .get_mut(len - 1)
-                .map(|pair| pair.1 = pair.1.map(|i| i + 1).or(Some(0)))
-                .ok_or_else(|| Error::serde(format!("last key is not found in {} keys", len)))
+    fn write(&mut self, value: Value) -> Result<()> {
+        if self.path.is_root() {
+            self.output = value;
         } else {
-            Err(Error::serde("keys is empty".to_string()))
+            self.output.set(&self.path, value)?;
         }
+        Ok(())
     }
 
-    fn make_full_key(&self, key: &str) -> String {
-        let len = self.keys.len();
-        if len > 0 {
-            if let Some(&(ref prev_key, index)) = self.keys.get(len - 1) {
-                return if let Some(index) = index {
-                    format!("{}[{}]/{}", prev_key, index, key)
-                } else {
-                    format!("{}/{}", prev_key, key)
-                };
-            }
-        }
-        format!("/{}", key)
+    fn begin_seq(&mut self) -> Result<()> {
+        self.write(Value::Array(Vec::new()))?;
+        self.indices.push(0);
+        Ok(())
     }
 
-    fn push_key(&mut self, key: &str) {
-        let full_key = self.make_full_key(key);
-        self.keys.push((full_key, None));
+    fn end_seq(&mut self) {
+        self.indices.pop();
     }
 
-    fn pop_key(&mut self) -> Option<(String, Option<usize>)> {
-        self.keys.pop()
+    fn serialize_next_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let index = self
+            .indices
+            .last_mut()
+            .ok_or_else(|| Error::serde("element outside of a sequence"))?;
+        let node = path::PathNode::Index(*index as isize);
+        *index += 1;
+        self.path.push(node);
+        let result = value.serialize(&mut *self);
+        self.path.pop();
+        result
+    }
+
+    fn serialize_at_key<T>(&mut self, key: String, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        self.path.push(path::PathNode::Identifier(key));
+        let result = value.serialize(&mut *self);
+        self.path.pop();
+        result
     }
 }
 
-impl<'a> ser::Serializer for &'a mut ValueSerializer {
+impl ser::Serializer for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
     type SerializeSeq = Self;
@@ -121,15 +162,15 @@ impl<'a> ser::Serializer for &'a mut ValueSerializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        if v > (i64::max_value() as u64) {
-            Err(Error::serde(format!(
-                "value {} is greater than the max {}",
-                v,
-                i64::max_value()
-            )))
-        } else {
-            self.serialize_i64(v as i64)
-        }
+        self.serialize_primitive(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        self.serialize_primitive(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        self.serialize_primitive(v)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
@@ -182,7 +223,7 @@ impl<'a> ser::Serializer for &'a mut ValueSerializer {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok> {
-        self.serialize_str(&variant)
+        self.serialize_str(variant)
     }
 
     fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
@@ -202,13 +243,11 @@ impl<'a> ser::Serializer for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.push_key(&variant);
-        value.serialize(&mut *self)?;
-        self.pop_key();
-        Ok(())
+        self.serialize_at_key(variant.to_string(), value)
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.begin_seq()?;
         Ok(self)
     }
 
@@ -231,16 +270,19 @@ impl<'a> ser::Serializer for &'a mut ValueSerializer {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.push_key(&variant);
+        self.path
+            .push(path::PathNode::Identifier(variant.to_string()));
+        self.begin_seq()?;
         Ok(self)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.write(Value::Map(Default::default()))?;
         Ok(self)
     }
 
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        Ok(self)
+        self.serialize_map(Some(len))
     }
 
     fn serialize_struct_variant(
@@ -250,12 +292,14 @@ impl<'a> ser::Serializer for &'a mut ValueSerializer {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.push_key(&variant);
+        self.path
+            .push(path::PathNode::Identifier(variant.to_string()));
+        self.write(Value::Map(Default::default()))?;
         Ok(self)
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut ValueSerializer {
+impl ser::SerializeSeq for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -263,17 +307,16 @@ impl<'a> ser::SerializeSeq for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.inc_last_key_index()?;
-        value.serialize(&mut **self)?;
-        Ok(())
+        self.serialize_next_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
+        self.end_seq();
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut ValueSerializer {
+impl ser::SerializeTuple for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -281,17 +324,16 @@ impl<'a> ser::SerializeTuple for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.inc_last_key_index()?;
-        value.serialize(&mut **self)?;
-        Ok(())
+        self.serialize_next_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
+        self.end_seq();
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut ValueSerializer {
+impl ser::SerializeTupleStruct for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -299,17 +341,16 @@ impl<'a> ser::SerializeTupleStruct for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.inc_last_key_index()?;
-        value.serialize(&mut **self)?;
-        Ok(())
+        self.serialize_next_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
+        self.end_seq();
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut ValueSerializer {
+impl ser::SerializeTupleVariant for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -317,18 +358,17 @@ impl<'a> ser::SerializeTupleVariant for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.inc_last_key_index()?;
-        value.serialize(&mut **self)?;
-        Ok(())
+        self.serialize_next_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.pop_key();
+        self.end_seq();
+        self.path.pop();
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeMap for &'a mut ValueSerializer {
+impl ser::SerializeMap for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -338,7 +378,7 @@ impl<'a> ser::SerializeMap for &'a mut ValueSerializer {
     {
         let key_serializer = ToStringSerializer;
         let key = key.serialize(key_serializer)?;
-        self.push_key(&key);
+        self.path.push(path::PathNode::Identifier(key));
         Ok(())
     }
 
@@ -346,9 +386,9 @@ impl<'a> ser::SerializeMap for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        value.serialize(&mut **self)?;
-        self.pop_key();
-        Ok(())
+        let result = value.serialize(&mut **self);
+        self.path.pop();
+        result
     }
 
     fn end(self) -> Result<Self::Ok> {
@@ -356,7 +396,7 @@ impl<'a> ser::SerializeMap for &'a mut ValueSerializer {
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut ValueSerializer {
+impl ser::SerializeStruct for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -364,10 +404,7 @@ impl<'a> ser::SerializeStruct for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.push_key(key);
-        value.serialize(&mut **self)?;
-        self.pop_key();
-        Ok(())
+        self.serialize_at_key(key.to_string(), value)
     }
 
     fn end(self) -> Result<Self::Ok> {
@@ -375,7 +412,7 @@ impl<'a> ser::SerializeStruct for &'a mut ValueSerializer {
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut ValueSerializer {
+impl ser::SerializeStructVariant for &mut ValueSerializer {
     type Ok = ();
     type Error = Error;
 
@@ -383,14 +420,11 @@ impl<'a> ser::SerializeStructVariant for &'a mut ValueSerializer {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.push_key(key);
-        value.serialize(&mut **self)?;
-        self.pop_key();
-        Ok(())
+        self.serialize_at_key(key.to_string(), value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.pop_key();
+        self.path.pop();
         Ok(())
     }
 }
@@ -444,6 +478,14 @@ impl ser::Serializer for ToStringSerializer {
         Ok(v.to_string())
     }
 
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        Ok(v.to_string())
+    }
+
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
         Ok(v.to_string())
     }
@@ -513,11 +555,11 @@ impl ser::Serializer for ToStringSerializer {
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Err(Error::serde("seq can't serialize to string".to_string()))
+        Err(Error::serde("seq can't serialize to string"))
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Err(Error::serde("tuple can't serialize to string".to_string()))
+        Err(Error::serde("tuple can't serialize to string"))
     }
 
     fn serialize_tuple_struct(self, name: &str, _len: usize) -> Result<Self::SerializeTupleStruct> {
@@ -541,9 +583,7 @@ impl ser::Serializer for ToStringSerializer {
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        Err(Error::serde(
-            "map can't serialize to string key".to_string(),
-        ))
+        Err(Error::serde("map can't serialize to string key"))
     }
 
     fn serialize_struct(self, name: &str, _len: usize) -> Result<Self::SerializeStruct> {
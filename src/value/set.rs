@@ -0,0 +1,167 @@
+use std::convert::TryInto;
+
+use crate::error::{Error, Result};
+use crate::path::{Path, PathNode};
+use crate::value::Value;
+
+/// How [`Value::set_with`] treats the values along the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetPolicy {
+    /// Replace any value that is not a container of the right kind, and
+    /// create missing ones. This is what [`Value::set`] does.
+    #[default]
+    Overwrite,
+    /// Create missing containers, but fail instead of replacing an existing
+    /// value that is not a container of the right kind. `Nil` is replaced.
+    ErrorOnConflict,
+    /// Like `ErrorOnConflict`, and also fail if any node before the last one
+    /// does not exist yet.
+    ErrorOnMissing,
+}
+
+/// The most `Nil` elements a set may add to reach an index. Larger gaps are
+/// out of range even under [`OutOfRange::Pad`], rather than allocating
+/// whatever the index asks for.
+pub(crate) const MAX_PADDING: usize = 1 << 20;
+
+/// How [`Value::set_with`] treats an index past the end of an array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfRange {
+    /// Grow the array with `Nil` until the index exists, adding at most
+    /// 2<sup>20</sup> elements. This is what [`Value::set`] does.
+    #[default]
+    Pad,
+    /// Fail if the array would need padding. An index equal to the length
+    /// still appends, as do `[-]` and `[^len]`.
+    Error,
+}
+
+/// Options for [`Value::set_with`].
+#[derive(Debug, Clone, Default)]
+pub struct SetOptions {
+    policy: SetPolicy,
+    out_of_range: OutOfRange,
+}
+
+impl SetOptions {
+    pub fn new() -> Self {
+        SetOptions::default()
+    }
+
+    /// Sets the policy for values along the path, see [`SetPolicy`].
+    pub fn policy(mut self, policy: SetPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets how indices past the end of an array are handled, see
+    /// [`OutOfRange`].
+    pub fn out_of_range(mut self, out_of_range: OutOfRange) -> Self {
+        self.out_of_range = out_of_range;
+        self
+    }
+}
+
+impl Value {
+    /// Like [`Value::set`], with control over what happens to existing and
+    /// missing values along the path.
+    ///
+    /// Conflicts are reported with the path of the value that would have been
+    /// replaced, missing nodes with the path of the first one missing.
+    pub fn set_with<P, IntoValue, IntoErr>(
+        &mut self,
+        path: P,
+        input_value: IntoValue,
+        options: &SetOptions,
+    ) -> Result<Value>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoValue: Into<Value>,
+        IntoErr: Into<Error>,
+    {
+        let input_value = input_value.into();
+        let path = path.try_into().map_err(|err| err.into())?;
+        if !path.is_singular() {
+            let replaced = self.replace_matches(&path, &input_value);
+            return Ok(Value::Array(replaced));
+        }
+
+        self.check_set_path(&path, options)?;
+        let mut target = self;
+        for node in path.iter() {
+            target = target.child_or_insert(node);
+        }
+        Ok(std::mem::replace(target, input_value))
+    }
+
+    /// Checks every step of the singular `path` before anything is changed,
+    /// so that a failed set leaves `self` untouched. Nodes that do not exist
+    /// yet are `Nil`.
+    pub(crate) fn check_set_path(&self, path: &Path, options: &SetOptions) -> Result<()> {
+        let nil = Value::Nil;
+        let mut current = Some(self);
+        for (i, node) in path.iter().enumerate() {
+            let value = current.unwrap_or(&nil);
+            value.check_set_step(path, i, node, options)?;
+            current = value.walk(std::slice::from_ref(node));
+        }
+        Ok(())
+    }
+
+    /// Checks that `self`, found at `path[..i]`, may be used to reach `node`.
+    fn check_set_step(
+        &self,
+        path: &Path,
+        i: usize,
+        node: &PathNode,
+        options: &SetOptions,
+    ) -> Result<()> {
+        let (fits, expected) = match (node, self) {
+            (PathNode::Identifier(_), Value::Map(_)) => (true, "a map"),
+            (PathNode::Identifier(_), _) => (false, "a map"),
+            (
+                PathNode::Index(_) | PathNode::Append | PathNode::InsertBefore(_),
+                Value::Array(_),
+            ) => (true, "an array"),
+            (PathNode::Index(_) | PathNode::Append | PathNode::InsertBefore(_), _) => {
+                (false, "an array")
+            }
+            _ => unreachable!("set_with only walks singular paths"),
+        };
+
+        let policy = options.policy;
+        if !fits && *self != Value::Nil && policy != SetPolicy::Overwrite {
+            let at: Path = path[..i].iter().cloned().collect();
+            return Err(Error::conflict(at, self.unexpected(), expected));
+        }
+
+        if let PathNode::Index(index) | PathNode::InsertBefore(index) = *node {
+            let len = match *self {
+                Value::Array(ref array) => array.len(),
+                _ => 0,
+            };
+            let padding = match Value::map_index(index, len) {
+                Some(at) => at.saturating_sub(len),
+                None => index.unsigned_abs() - len,
+            };
+            let limit = match options.out_of_range {
+                OutOfRange::Pad => MAX_PADDING,
+                OutOfRange::Error => 0,
+            };
+            if padding > limit {
+                let at: Path = path[..=i].iter().cloned().collect();
+                return Err(Error::out_of_range(at, len));
+            }
+        }
+
+        let is_intermediate = i + 1 < path.len();
+        if policy == SetPolicy::ErrorOnMissing && is_intermediate {
+            let exists = fits && self.walk(std::slice::from_ref(node)).is_some();
+            if !exists {
+                let at: Path = path[..=i].iter().cloned().collect();
+                return Err(Error::missing(at));
+            }
+        }
+        Ok(())
+    }
+}
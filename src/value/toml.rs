@@ -0,0 +1,83 @@
+use num_traits::ToPrimitive;
+
+use crate::error::{Error, Result, Unexpected};
+use crate::value::{Map, Value};
+
+const ORIGIN: &str = "TOML";
+
+impl Value {
+    /// Parses a TOML document into a map.
+    ///
+    /// Datetimes become strings in their RFC 3339 form, e.g.
+    /// `1979-05-27T07:32:00Z`, since `Value` has no datetime variant.
+    pub fn from_toml_str(s: &str) -> Result<Value> {
+        let table: toml::Table =
+            toml::from_str(s).map_err(|err| Error::format_parse(ORIGIN, err))?;
+        Ok(from_toml(toml::Value::Table(table)))
+    }
+
+    /// Renders this map as a TOML document.
+    ///
+    /// Arrays whose elements are all maps are written as arrays of tables
+    /// (`[[name]]`), nested maps as tables. `Nil` map entries are left out,
+    /// since TOML has no null. Fails if the value is not a map, if an array
+    /// contains `Nil`, or if an integer does not fit in 64 bits.
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string(&to_toml_table(self)?).map_err(|err| Error::format_parse(ORIGIN, err))
+    }
+
+    /// Like [`Value::to_toml_string`], with arrays spread over several lines.
+    pub fn to_toml_pretty(&self) -> Result<String> {
+        toml::to_string_pretty(&to_toml_table(self)?)
+            .map_err(|err| Error::format_parse(ORIGIN, err))
+    }
+}
+
+fn from_toml(toml: toml::Value) -> Value {
+    match toml {
+        toml::Value::String(value) => Value::String(value),
+        toml::Value::Integer(value) => Value::from(value),
+        toml::Value::Float(value) => Value::Float(value),
+        toml::Value::Boolean(value) => Value::Boolean(value),
+        toml::Value::Datetime(value) => Value::String(value.to_string()),
+        toml::Value::Array(array) => Value::Array(array.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Map(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, from_toml(value)))
+                .collect::<Map>(),
+        ),
+    }
+}
+
+fn to_toml_table(value: &Value) -> Result<toml::Table> {
+    match *value {
+        Value::Map(ref map) => {
+            let mut table = toml::Table::new();
+            for (key, value) in map {
+                if *value != Value::Nil {
+                    table.insert(key.clone(), to_toml(value)?);
+                }
+            }
+            Ok(table)
+        }
+        ref value => Err(Error::invalid_type(value.unexpected(), "a map")),
+    }
+}
+
+fn to_toml(value: &Value) -> Result<toml::Value> {
+    Ok(match *value {
+        Value::Nil => return Err(Error::invalid_type(Unexpected::Unit, "a TOML value")),
+        Value::Boolean(value) => toml::Value::Boolean(value),
+        Value::Integer(ref value) => match value.to_i64() {
+            Some(value) => toml::Value::Integer(value),
+            None => return Err(Error::too_large(value.clone())),
+        },
+        Value::Float(value) => toml::Value::Float(value),
+        Value::String(ref value) => toml::Value::String(value.clone()),
+        Value::Array(ref array) => {
+            toml::Value::Array(array.iter().map(to_toml).collect::<Result<_>>()?)
+        }
+        Value::Map(_) => toml::Value::Table(to_toml_table(value)?),
+    })
+}
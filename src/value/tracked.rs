@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::path::{Path, PathNode};
+use crate::value::merge::MergeEvent;
+use crate::value::{MergeOptions, SetOptions, Value};
+
+/// Names the source a value came from, e.g. a file name or `env`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin(Arc<str>);
+
+impl Origin {
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Origin(Arc::from(name.as_ref()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'a> From<&'a str> for Origin {
+    fn from(name: &'a str) -> Self {
+        Origin::new(name)
+    }
+}
+
+impl From<String> for Origin {
+    fn from(name: String) -> Self {
+        Origin::new(name)
+    }
+}
+
+/// A [`Value`] that remembers which source set each of its parts.
+///
+/// Origins are kept per path; a value without an origin of its own has the
+/// origin of its nearest ancestor. Merging and setting replace the origins of
+/// everything they overwrite.
+#[derive(Debug, Clone, Default)]
+pub struct Tracked {
+    value: Value,
+    // `None` marks a value that came from an untracked source, so that it
+    // does not inherit the origin of its parent.
+    origins: HashMap<Path, Option<Origin>>,
+}
+
+impl Tracked {
+    /// Wraps `value`, recording `origin` for all of it.
+    pub fn new<O: Into<Origin>>(value: Value, origin: O) -> Self {
+        let mut origins = HashMap::new();
+        origins.insert(Path::root(), Some(origin.into()));
+        Tracked { value, origins }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Returns the origin of the value at `path`.
+    ///
+    /// Returns `Ok(None)` if nothing matches or the value has no origin; for
+    /// a non-singular path the first match is used.
+    pub fn origin<P, IntoErr>(&self, path: P) -> Result<Option<&Origin>>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        let first = self.value.query(&path)?.next();
+        Ok(match first {
+            Some((concrete, _)) => self.origin_at(&concrete),
+            None => None,
+        })
+    }
+
+    /// Converts the value at `path` into `T`, like [`Value::get`]. Type
+    /// errors name the origin of the value.
+    pub fn get<T, P, IntoErr>(&self, path: P) -> Result<Option<T>>
+    where
+        T: TryFrom<Value, Error = Error>,
+        P: TryInto<Path, Error = IntoErr>,
+        IntoErr: Into<Error>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        self.value.get::<T, _, _>(&path).map_err(|err| {
+            let origin = self.origin(&path).ok().flatten();
+            err.with_origin(origin)
+        })
+    }
+
+    /// Sets the value at `path` like [`Value::set`], recording `origin` for
+    /// it. Returns the old value.
+    pub fn set<P, IntoValue, IntoErr, O>(
+        &mut self,
+        path: P,
+        input_value: IntoValue,
+        origin: O,
+    ) -> Result<Value>
+    where
+        P: TryInto<Path, Error = IntoErr>,
+        IntoValue: Into<Value>,
+        IntoErr: Into<Error>,
+        O: Into<Origin>,
+    {
+        let path = path.try_into().map_err(|err| err.into())?;
+        let origin = Some(origin.into());
+        if !path.is_singular() {
+            let targets: Vec<Path> = self.value.query(&path)?.map(|(path, _)| path).collect();
+            let replaced = self.value.set(&path, input_value)?;
+            for target in targets {
+                self.record(&target, origin.clone());
+            }
+            return Ok(replaced);
+        }
+
+        // Check first, so that the origins are not moved for a failing set.
+        self.value.check_set_path(&path, &SetOptions::default())?;
+        let target = self.prepare_set(&path);
+        let replaced = self.value.set(&path, input_value)?;
+        self.record(&target, origin);
+        Ok(replaced)
+    }
+
+    /// Merges `source` into `self` like [`Value::merge`], taking over the
+    /// origins of every value that `source` sets.
+    pub fn merge(&mut self, source: Tracked) -> Result<()> {
+        self.merge_with(source, &MergeOptions::default())
+    }
+
+    /// Like [`Tracked::merge`], with the policies given by `options`.
+    pub fn merge_with(&mut self, source: Tracked, options: &MergeOptions) -> Result<()> {
+        let Tracked {
+            value: source_value,
+            origins: source_origins,
+        } = source;
+        let source = Tracked {
+            value: Value::Nil,
+            origins: source_origins,
+        };
+        let origins = &mut self.origins;
+        self.value
+            .merge_observed(source_value, options, &mut |event| match event {
+                MergeEvent::Replaced {
+                    target,
+                    source: source_path,
+                } => {
+                    origins.retain(|path, _| !path.starts_with(target));
+                    origins.insert(target.clone(), source.origin_at(source_path).cloned());
+                    for (path, origin) in &source.origins {
+                        if let Some(rest) = path.strip_prefix(source_path) {
+                            if !rest.is_root() {
+                                origins.insert(target.join(&rest), origin.clone());
+                            }
+                        }
+                    }
+                }
+                MergeEvent::Removed { target } => {
+                    origins.retain(|path, _| !path.starts_with(target));
+                }
+            })
+    }
+
+    /// Returns the origin recorded at `path` or at its nearest ancestor.
+    fn origin_at(&self, path: &Path) -> Option<&Origin> {
+        (0..=path.len())
+            .rev()
+            .find_map(|len| {
+                let prefix: Path = path[..len].iter().cloned().collect();
+                self.origins.get(&prefix)
+            })
+            .and_then(Option::as_ref)
+    }
+
+    /// Replaces the origins below `path` with `origin`.
+    fn record(&mut self, path: &Path, origin: Option<Origin>) {
+        self.origins
+            .retain(|recorded, _| !recorded.starts_with(path));
+        self.origins.insert(path.clone(), origin);
+    }
+
+    /// Resolves the singular `path` to the location [`Value::set`] will
+    /// write, and moves the origins of array elements that the set is going
+    /// to shift or replace.
+    fn prepare_set(&mut self, path: &Path) -> Path {
+        let mut concrete = Path::root();
+        let mut current = Some(&self.value);
+        let mut shifts = Vec::new();
+        let mut cleared = None;
+        for node in path.iter() {
+            let fits = match (node, current) {
+                (PathNode::Identifier(_), Some(Value::Map(_))) => true,
+                (PathNode::Identifier(_), _) => false,
+                (_, Some(Value::Array(_))) => true,
+                _ => false,
+            };
+            if !fits && current.is_some() && cleared.is_none() {
+                cleared = Some(concrete.clone());
+            }
+
+            let len = match current {
+                Some(Value::Array(array)) if fits => array.len(),
+                _ => 0,
+            };
+            // Whether the set creates a new element instead of reaching an
+            // existing one.
+            let mut fresh = false;
+            let resolved = match *node {
+                PathNode::Identifier(ref key) => PathNode::Identifier(key.clone()),
+                PathNode::Append => PathNode::Index(len as isize),
+                PathNode::Index(index) | PathNode::InsertBefore(index) => {
+                    let insert = matches!(*node, PathNode::InsertBefore(_));
+                    match Value::map_index(index, len) {
+                        Some(at) => {
+                            if insert && at <= len {
+                                shifts.push((concrete.clone(), at, 1));
+                                fresh = true;
+                            }
+                            PathNode::Index(at as isize)
+                        }
+                        None => {
+                            shifts.push((concrete.clone(), 0, index.unsigned_abs() - len));
+                            fresh = true;
+                            PathNode::Index(0)
+                        }
+                    }
+                }
+                _ => unreachable!("only singular paths are resolved"),
+            };
+            current = match current {
+                Some(value) if !fresh => value.walk(std::slice::from_ref(&resolved)),
+                _ => None,
+            };
+            concrete.push(resolved);
+        }
+
+        if let Some(cleared) = cleared {
+            self.origins.retain(|recorded, _| {
+                recorded.len() <= cleared.len() || !recorded.starts_with(&cleared)
+            });
+        }
+        for (array, from, by) in shifts {
+            self.origins = self
+                .origins
+                .drain()
+                .map(|(recorded, origin)| (shift(recorded, &array, from, by), origin))
+                .collect();
+        }
+        concrete
+    }
+}
+
+impl From<Value> for Tracked {
+    /// Wraps `value` without an origin.
+    fn from(value: Value) -> Self {
+        Tracked {
+            value,
+            origins: HashMap::new(),
+        }
+    }
+}
+
+/// Moves `path` back by `by` elements if it is in `array` at or after `from`.
+fn shift(mut path: Path, array: &Path, from: usize, by: usize) -> Path {
+    if path.len() > array.len() && path.starts_with(array) {
+        if let PathNode::Index(ref mut index) = path[array.len()] {
+            if *index as usize >= from {
+                *index += by as isize;
+            }
+        }
+    }
+    path
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::value::{Map, Value};
+
+const ORIGIN: &str = "YAML";
+
+impl Value {
+    /// Parses a YAML document. Fails if the input holds more than one
+    /// document, see [`Value::from_yaml_documents`].
+    ///
+    /// Aliases are replaced by a copy of their anchored value, and merge keys
+    /// (`<<: *defaults`) are applied, with the keys of the map itself taking
+    /// precedence. Tagged values (`!Tag value`) become a map with the tag as
+    /// the only key. Map keys must be strings, numbers or booleans.
+    pub fn from_yaml_str(s: &str) -> Result<Value> {
+        let value = serde_norway::from_str(s).map_err(yaml_error)?;
+        apply_merge_keys(value)
+    }
+
+    /// Parses a YAML document from `reader`, see [`Value::from_yaml_str`].
+    pub fn from_yaml_reader<R: io::Read>(reader: R) -> Result<Value> {
+        let value = serde_norway::from_reader(reader).map_err(yaml_error)?;
+        apply_merge_keys(value)
+    }
+
+    /// Parses every document of a YAML stream, e.g. one with several
+    /// `---`-separated manifests.
+    pub fn from_yaml_documents(s: &str) -> Result<Vec<Value>> {
+        serde_norway::Deserializer::from_str(s)
+            .map(|document| apply_merge_keys(Value::deserialize(document).map_err(yaml_error)?))
+            .collect()
+    }
+
+    /// Renders this value as a YAML document.
+    pub fn to_yaml_string(&self) -> Result<String> {
+        serde_norway::to_string(self).map_err(yaml_error)
+    }
+
+    /// Renders `documents` as one YAML stream, separated by `---`.
+    pub fn to_yaml_documents(documents: &[Value]) -> Result<String> {
+        let mut output = Vec::new();
+        let mut serializer = serde_norway::Serializer::new(&mut output);
+        for document in documents {
+            document.serialize(&mut serializer).map_err(yaml_error)?;
+        }
+        drop(serializer);
+        String::from_utf8(output).map_err(|err| Error::format_parse(ORIGIN, err))
+    }
+}
+
+fn yaml_error(err: serde_norway::Error) -> Error {
+    Error::format_parse(ORIGIN, err)
+}
+
+/// The key that merges other maps into a YAML map.
+const MERGE_KEY: &str = "<<";
+
+/// Replaces every `<<` entry with the entries of the map, or the list of
+/// maps, it names. Keys of the map itself win over merged ones, and earlier
+/// maps in a list win over later ones. Merged keys take the place of `<<`.
+///
+/// serde_norway only applies merge keys to its own `Value`, which cannot hold
+/// integers past 64 bits, so they are applied after conversion.
+fn apply_merge_keys(value: Value) -> Result<Value> {
+    Ok(match value {
+        Value::Array(array) => Value::Array(
+            array
+                .into_iter()
+                .map(apply_merge_keys)
+                .collect::<Result<_>>()?,
+        ),
+        Value::Map(map) => {
+            let explicit: HashSet<String> = map
+                .keys()
+                .filter(|key| *key != MERGE_KEY)
+                .cloned()
+                .collect();
+            let mut merged = Map::with_capacity(map.len());
+            for (key, value) in map {
+                let value = apply_merge_keys(value)?;
+                if key != MERGE_KEY {
+                    merged.insert(key, value);
+                    continue;
+                }
+                let sources = match value {
+                    Value::Array(sources) => sources,
+                    source => vec![source],
+                };
+                for source in sources {
+                    let source = match source {
+                        Value::Map(source) => source,
+                        other => {
+                            return Err(Error::format_parse(
+                                ORIGIN,
+                                Error::serde(format!(
+                                    "merge key `<<` expects a map or a list of maps, found {}",
+                                    other.unexpected()
+                                )),
+                            ))
+                        }
+                    };
+                    for (key, value) in source {
+                        if !explicit.contains(&key) && !merged.contains_key(&key) {
+                            merged.insert(key, value);
+                        }
+                    }
+                }
+            }
+            Value::Map(merged)
+        }
+        value => value,
+    })
+}